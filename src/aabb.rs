@@ -0,0 +1,141 @@
+use crate::{common::math::Interval, ray::Ray, vec3::Point3};
+
+/// An axis-aligned bounding box, represented as the intersection of three intervals (one along
+/// each axis). Used by the BVH to quickly reject rays that can't possibly hit the objects
+/// contained within the box.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Aabb {
+    x: Interval,
+    y: Interval,
+    z: Interval,
+}
+
+impl Aabb {
+    /// The box containing nothing - surrounding it with any other box yields that other box
+    pub const EMPTY: Aabb = Aabb {
+        x: Interval::EMPTY,
+        y: Interval::EMPTY,
+        z: Interval::EMPTY,
+    };
+
+    pub fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        Aabb { x, y, z }.pad_to_minimums()
+    }
+
+    /// Constructs the box with the two points `a` and `b` at opposite corners. The points need
+    /// not be ordered in any particular way.
+    pub fn from_points(a: Point3, b: Point3) -> Self {
+        Aabb::new(
+            Interval::new(a.0.min(b.0), a.0.max(b.0)),
+            Interval::new(a.1.min(b.1), a.1.max(b.1)),
+            Interval::new(a.2.min(b.2), a.2.max(b.2)),
+        )
+    }
+
+    /// Returns the tightest box enclosing both `a` and `b`
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Self {
+        Aabb {
+            x: Interval::surrounding(a.x, b.x),
+            y: Interval::surrounding(a.y, b.y),
+            z: Interval::surrounding(a.z, b.z),
+        }
+    }
+
+    /// Returns this box grown by `distance` on every side
+    pub fn expand(&self, distance: f64) -> Self {
+        Aabb {
+            x: self.x.expand(2.0 * distance),
+            y: self.y.expand(2.0 * distance),
+            z: self.z.expand(2.0 * distance),
+        }
+    }
+
+    /// Returns the interval along the given axis (0 => x, 1 => y, 2 => z)
+    pub fn axis_interval(&self, axis: usize) -> Interval {
+        match axis {
+            1 => self.y,
+            2 => self.z,
+            _ => self.x,
+        }
+    }
+
+    /// Returns the index of the axis along which this box is the longest
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() {
+                0
+            } else {
+                2
+            }
+        } else if self.y.size() > self.z.size() {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            0.5 * (self.x.min() + self.x.max()),
+            0.5 * (self.y.min() + self.y.max()),
+            0.5 * (self.z.min() + self.z.max()),
+        )
+    }
+
+    /// Returns false for boxes of infinite extent, e.g those of infinite planes
+    pub fn is_bounded(&self) -> bool {
+        [self.x, self.y, self.z]
+            .iter()
+            .all(|interval| interval.min().is_finite() && interval.max().is_finite())
+    }
+
+    /// Uses the "slab" method - the ray hits the box iff the parametric intervals over which it
+    /// lies within each pair of axis-aligned planes all overlap
+    pub fn hit(&self, ray: &Ray, interval: Interval) -> bool {
+        self.overlap(ray, interval).is_some()
+    }
+
+    /// Returns the part of the given interval over which the ray lies within the box, if any
+    pub fn overlap(&self, ray: &Ray, interval: Interval) -> Option<Interval> {
+        let origin = ray.origin();
+        let direction = ray.direction();
+        let (mut t_min, mut t_max) = (interval.min(), interval.max());
+
+        for axis in 0..3 {
+            let axis_interval = self.axis_interval(axis);
+            let (o, d) = match axis {
+                0 => (origin.0, direction.0),
+                1 => (origin.1, direction.1),
+                _ => (origin.2, direction.2),
+            };
+            let d_inv = 1.0 / d;
+
+            let t0 = (axis_interval.min() - o) * d_inv;
+            let t1 = (axis_interval.max() - o) * d_inv;
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max <= t_min {
+                return None;
+            }
+        }
+
+        Some(Interval::new(t_min, t_max))
+    }
+
+    /// Avoids degenerate (zero-width) boxes, e.g for flat primitives lying in an axis-aligned plane
+    fn pad_to_minimums(mut self) -> Self {
+        const DELTA: f64 = 0.0001;
+        if self.x.size() < DELTA {
+            self.x = self.x.expand(DELTA);
+        }
+        if self.y.size() < DELTA {
+            self.y = self.y.expand(DELTA);
+        }
+        if self.z.size() < DELTA {
+            self.z = self.z.expand(DELTA);
+        }
+        self
+    }
+}
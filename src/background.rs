@@ -0,0 +1,71 @@
+use image::Rgb32FImage;
+
+use crate::{
+    color::Color,
+    common::math::{lerp, PI},
+    config::BackgroundConfig,
+    vec3::Vec3,
+};
+
+/// The radiance seen by rays that escape the scene without hitting anything
+pub enum Background {
+    /// The same color in every direction (black for a dark interior)
+    Solid(Color),
+
+    /// Blends from `bottom` to `top` based on how closely the ray direction aligns with `up`
+    Gradient { bottom: Color, top: Color, up: Vec3 },
+
+    /// An equirectangular (latitude-longitude) environment map with +Y as the up axis
+    EnvironmentMap(Rgb32FImage),
+}
+
+impl Background {
+    pub fn new(config: &BackgroundConfig) -> Self {
+        match config {
+            BackgroundConfig::Solid { color } => Background::Solid(Color::from(color.clone())),
+            BackgroundConfig::Gradient { bottom, top, up } => Background::Gradient {
+                bottom: Color::from(bottom.clone()),
+                top: Color::from(top.clone()),
+                up: Vec3::from(up.clone()).into_unit(),
+            },
+            BackgroundConfig::EnvironmentMap { file } => Background::EnvironmentMap(
+                image::open(file)
+                    .unwrap_or_else(|err| panic!("loading environment map {}: {}", file, err))
+                    .into_rgb32f(),
+            ),
+        }
+    }
+
+    pub fn color(&self, direction: Vec3) -> Color {
+        let unit_direction = direction.into_unit();
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top, up } => {
+                let t = 0.5 * (unit_direction.dot(*up) + 1.0);
+                lerp(*bottom, *top, t)
+            }
+            Background::EnvironmentMap(image) => {
+                // Longitude maps onto the horizontal axis, latitude onto the vertical one (with
+                // the top row looking straight up)
+                let u = 0.5 + unit_direction.0.atan2(-unit_direction.2) / (2.0 * PI);
+                let v = unit_direction.1.clamp(-1.0, 1.0).acos() / PI;
+
+                let x = ((u * image.width() as f64) as u32).min(image.width() - 1);
+                let y = ((v * image.height() as f64) as u32).min(image.height() - 1);
+                let pixel = image.get_pixel(x, y);
+                Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64)
+            }
+        }
+    }
+}
+
+/// The blue-to-white sky used when no background is configured
+impl Default for Background {
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: Color::from(1.0),
+            top: Color::new(0.5, 0.7, 1.0),
+            up: Vec3::new(0.0, 1.0, 0.0),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    common::math::Interval,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+};
+
+/// A node in a bounding volume hierarchy. Each node stores the bounding box enclosing all of its
+/// descendants, so a ray that misses the box can skip the entire subtree. Children are either
+/// other `BvhNode`s or (at the leaves) the primitives themselves.
+pub struct BvhNode {
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    /// Builds the hierarchy by recursively splitting the objects at the median of their centroids
+    /// along the longest axis of the centroid bounds
+    pub fn new(mut objects: Vec<Arc<dyn Hittable>>) -> Self {
+        if objects.is_empty() {
+            panic!("cannot build a BVH with no objects");
+        }
+        BvhNode::from_slice(&mut objects)
+    }
+
+    fn from_slice(objects: &mut [Arc<dyn Hittable>]) -> Self {
+        let bbox = objects.iter().fold(Aabb::EMPTY, |bbox, object| {
+            Aabb::surrounding(&bbox, &object.bounding_box())
+        });
+
+        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match objects.len() {
+            1 => (objects[0].clone(), objects[0].clone()),
+            2 => (objects[0].clone(), objects[1].clone()),
+            n => {
+                let centroid_bounds = objects.iter().fold(Aabb::EMPTY, |bbox, object| {
+                    let centroid = object.bounding_box().centroid();
+                    Aabb::surrounding(&bbox, &Aabb::from_points(centroid, centroid))
+                });
+                let axis = centroid_bounds.longest_axis();
+
+                objects.sort_by(|a, b| {
+                    let a = a.bounding_box().axis_interval(axis);
+                    let b = b.bounding_box().axis_interval(axis);
+                    (a.min() + a.max()).total_cmp(&(b.min() + b.max()))
+                });
+
+                let (left, right) = objects.split_at_mut(n / 2);
+                (
+                    Arc::new(BvhNode::from_slice(left)),
+                    Arc::new(BvhNode::from_slice(right)),
+                )
+            }
+        };
+
+        BvhNode { left, right, bbox }
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        if !self.bbox.hit(ray, interval) {
+            return None;
+        }
+
+        let left_record = self.left.hit(ray, interval);
+        // No need to look any further than the left hit (if any) in the right subtree
+        let closest_so_far = left_record.as_ref().map_or(interval.max(), |rec| rec.t);
+        let right_record = self
+            .right
+            .hit(ray, Interval::new(interval.min(), closest_so_far));
+
+        right_record.or(left_record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
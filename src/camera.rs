@@ -1,13 +1,18 @@
+use std::sync::Arc;
+
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use std::io::Write;
 
 use crate::{
-    color::{write_color, Color},
-    common::math::{deg_to_rad, lerp, random, Interval, INFINITY},
+    background::Background,
+    color::Color,
+    common::math::{deg_to_rad, random, random_in_range, Interval, INFINITY},
     config::CameraConfig,
-    hittable::Hittable,
+    hittable::{HitRecord, Hittable},
+    material::{Material, Traversal},
+    output::Framebuffer,
     ray::Ray,
+    spectrum::Wavelengths,
     vec3::{Point3, Vec3},
     world::World,
 };
@@ -83,36 +88,46 @@ pub struct Camera {
     defocus_disc_v: Vec3,
     samples_per_pixel: i32,
     max_ray_bounces: i32,
+    russian_roulette_depth: i32,
+    shutter_open: f64,
+    shutter_close: f64,
+    /// Whether paths carry sampled wavelengths rather than RGB colors
+    spectral: bool,
     image_properties: ImageProperties,
     viewport_properties: ViewportProperties,
+    background: Background,
 }
 
 impl Camera {
-    pub fn new(config: &CameraConfig) -> Self {
+    pub fn new(config: &CameraConfig, background: Background) -> Self {
         let image_properties = ImageProperties::new(config);
         let viewport_properties = ViewportProperties::new(config, &image_properties);
 
         let (u, v, _) = Camera::get_basis_vectors(config);
 
         // Calculate the camera defocus disc basis vectors
-        let defocus_radius =
-            config.focus_distance * deg_to_rad(config.defocus_angle as f64 / 2.0).tan();
+        let defocus_radius = config.focus_distance * deg_to_rad(config.defocus_angle / 2.0).tan();
         let defocus_disc_u = u * defocus_radius;
         let defocus_disc_v = v * defocus_radius;
 
         Camera {
             center: Point3::from(config.lookfrom.clone()),
-            defocus_angle: config.defocus_angle as f64,
+            defocus_angle: config.defocus_angle,
             defocus_disc_u,
             defocus_disc_v,
             samples_per_pixel: config.samples_per_pixel,
             max_ray_bounces: config.max_ray_bounces,
+            russian_roulette_depth: config.russian_roulette_depth,
+            shutter_open: config.shutter_open,
+            shutter_close: config.shutter_close,
+            spectral: config.spectral,
             image_properties,
             viewport_properties,
+            background,
         }
     }
 
-    pub fn render(&self, world: &World, out: &mut impl Write) {
+    pub fn render(&self, world: &World) -> Framebuffer {
         if self.image_properties.height < 1 {
             panic!("IMAGE_HEIGHT is way too small, use a larger width");
         }
@@ -126,12 +141,10 @@ impl Camera {
             self.viewport_properties.width, self.viewport_properties.height
         );
 
-        writeln!(
-            out,
-            "P3\n{} {}\n255\n",
-            self.image_properties.width, self.image_properties.height
-        )
-        .expect("writing header");
+        let mut framebuffer = Framebuffer::new(
+            self.image_properties.width as usize,
+            self.image_properties.height as usize,
+        );
 
         // More elegant progress bar than just eprintin'
         let bar = ProgressBar::new(self.image_properties.height as u64);
@@ -152,16 +165,17 @@ impl Camera {
                     // Anti-aliasing
                     (0..self.samples_per_pixel).for_each(|_| {
                         let ray = self.get_ray(i, j);
-                        pixel_color =
-                            pixel_color + self.ray_color(&ray, world, self.max_ray_bounces);
+                        pixel_color = pixel_color + self.ray_color(&ray, world);
                     });
-                    pixel_color
+                    pixel_color / self.samples_per_pixel as f64
                 })
                 .collect();
-            for pixel_color in pixel_colors {
-                write_color(out, pixel_color / self.samples_per_pixel as f64);
-            }
+            framebuffer
+                .row_mut(j as usize)
+                .copy_from_slice(&pixel_colors);
         }
+
+        framebuffer
     }
 
     /// Computes the basis vectors for the camera's orientation
@@ -177,26 +191,156 @@ impl Camera {
         (u, v, w)
     }
 
-    fn ray_color<T: Hittable>(&self, ray: &Ray, obj: &T, depth: i32) -> Color {
-        if depth <= 0 {
+    /// Traces a path through the scene, accumulating the light that reaches the camera along it.
+    /// In spectral mode the path is traced at a few sampled wavelengths, with every color along
+    /// the way turned into a spectrum, and the result turned back into RGB at the end.
+    fn ray_color(&self, ray: &Ray, world: &World) -> Color {
+        let mut wavelengths = self.spectral.then(Wavelengths::sample);
+        let mut radiance = Color::from(0.0);
+        // The fraction of light arriving along the current ray that makes it back to the camera
+        let mut throughput = Color::from(1.0);
+        let mut ray = ray.with_wavelength(wavelengths.map(|w| w.hero()));
+        // The density with which the current ray's direction was sampled at the previous bounce,
+        // or None if it couldn't have been found by sampling the lights instead (for camera rays
+        // and specular bounces)
+        let mut scattering_pdf = None;
+        // The materials of the objects the ray is inside of, innermost last. Pushed when the path
+        // refracts into an object and popped when it refracts back out, so objects nested inside
+        // others (and surfaces that don't enclose anything) are handled correctly.
+        let mut media: Vec<Arc<Material>> = Vec::new();
+
+        for depth in 0..self.max_ray_bounces {
+            // Having the interval start at 0.001 helps resolve "shadow acne"
+            let hit = world.hit(&ray, Interval::new(0.001, INFINITY));
+
+            // Any medium the ray is traveling through dims the light along the way, or may
+            // scatter it before it gets to the next surface
+            let interior_scatter = media.last().and_then(|medium| {
+                let distance = hit
+                    .as_ref()
+                    .map_or(INFINITY, |rec| rec.t * ray.direction().length());
+                match medium.traverse(&ray, distance, throughput) {
+                    Traversal::Transmitted(transmittance) => {
+                        throughput = throughput * upsample(transmittance, wavelengths);
+                        None
+                    }
+                    Traversal::Scattered(scatter_record) => Some(scatter_record),
+                }
+            });
+
+            let scatter_record = match (interior_scatter, hit) {
+                (Some(scatter_record), _) => {
+                    // The lights can't be seen from inside an object, so there's no point in
+                    // sampling them
+                    scattering_pdf = None;
+                    scatter_record
+                }
+                (None, None) => {
+                    let background = self.background.color(ray.direction());
+                    radiance = radiance + throughput * upsample(background, wavelengths);
+                    break;
+                }
+                (None, Some(rec)) => {
+                    // Any light found by sampling the scattered direction could also have been
+                    // found by sampling the lights at the previous bounce, so weigh it against
+                    // that strategy. Lights that can't be sampled (e.g infinite planes) have a
+                    // zero density there and always count.
+                    let mut emitted = rec.material.emitted(&rec);
+                    if let Some(scattering_pdf) =
+                        scattering_pdf.filter(|_| rec.material.is_emissive())
+                    {
+                        emitted = emitted * power_heuristic(scattering_pdf, world.pdf_value(&ray));
+                    }
+                    radiance = radiance + throughput * upsample(emitted, wavelengths);
+
+                    // Dispersive materials send each wavelength in a different direction (or
+                    // reflect them by amounts only worked out for one), so only the hero
+                    // wavelength can be followed from here on
+                    if let Some(wavelengths) = wavelengths
+                        .as_mut()
+                        .filter(|_| rec.material.is_dispersive())
+                    {
+                        throughput = throughput * wavelengths.collapse();
+                    }
+
+                    let Some(scatter_record) = rec.material.scatter(&ray, &rec) else {
+                        break;
+                    };
+                    scattering_pdf = if scatter_record.is_specular {
+                        None
+                    } else {
+                        radiance = radiance
+                            + throughput * self.sample_lights(&ray, &rec, world, wavelengths);
+                        Some(scatter_record.pdf)
+                    };
+
+                    // The normal faces the side the ray arrived from, so refracted rays leave on
+                    // the other side of it
+                    let refracted = scatter_record.scattered.direction().dot(rec.normal) < 0.0;
+                    if refracted && rec.material.encloses_medium() {
+                        if rec.did_hit_front_frace {
+                            media.push(rec.material.clone());
+                        } else if let Some(index) = media
+                            .iter()
+                            .rposition(|medium| Arc::ptr_eq(medium, &rec.material))
+                        {
+                            media.remove(index);
+                        }
+                    }
+                    scatter_record
+                }
+            };
+            throughput = throughput * upsample(scatter_record.attenuation, wavelengths);
+
+            // Russian roulette - randomly end dim paths, which contribute little, and make up for
+            // it by scaling up the ones that survive so the estimate stays unbiased
+            if depth + 1 >= self.russian_roulette_depth {
+                let survival_probability = throughput.reduce(f64::max).min(1.0);
+                if random() >= survival_probability {
+                    break;
+                }
+                throughput = throughput / survival_probability;
+            }
+
+            ray = scatter_record.scattered.with_wavelength(ray.wavelength());
+        }
+
+        wavelengths.map_or(radiance, |wavelengths| wavelengths.to_rgb(radiance))
+    }
+
+    /// Next event estimation - estimates the light arriving at the hit point directly from the
+    /// lights by casting a shadow ray towards a random point on one of them. Weighted against
+    /// finding the same light by sampling the material with multiple importance sampling.
+    fn sample_lights(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        world: &World,
+        wavelengths: Option<Wavelengths>,
+    ) -> Color {
+        if !world.is_emissive() {
             return Color::from(0.0);
         }
 
-        // Having the interval start at 0.001 helps resolve "shadow acne"
-        if let Some(rec) = obj.hit(&ray, Interval::new(0.001, INFINITY)) {
-            rec.material
-                .scatter(ray, &rec)
-                .map(|scatter_record| {
-                    scatter_record.attenuation
-                        * self.ray_color(&scatter_record.scattered, obj, depth - 1)
-                })
-                .unwrap_or_else(|| Color::from(0.0))
-        } else {
-            // Generates a blue-to-white gradient background
-            let unit_direction = ray.direction().into_unit();
-            let t = 0.5 * (unit_direction.1 + 1.0);
-            lerp(Color::from(1.0), Color::new(0.5, 0.7, 1.0), t)
+        let direction = world.random(rec.point, ray.time());
+        let shadow_ray = Ray::new(rec.point, direction, ray.time());
+        let light_pdf = world.pdf_value(&shadow_ray);
+        let scattered = rec.material.eval(ray, rec, direction);
+        if light_pdf <= 0.0 || scattered.is_near_zero() {
+            return Color::from(0.0);
         }
+
+        // Whatever the shadow ray hits first is what lights the point, so anything that doesn't
+        // emit light in the way leaves it in shadow
+        world
+            .hit(&shadow_ray, Interval::new(0.001, INFINITY))
+            .map_or(Color::from(0.0), |light_rec| {
+                let weight = power_heuristic(light_pdf, rec.material.pdf(ray, rec, direction));
+                let emitted = light_rec.material.emitted(&light_rec);
+                upsample(scattered, wavelengths)
+                    * upsample(emitted, wavelengths)
+                    * (weight / light_pdf)
+            })
     }
 
     /// Constructs a ray originating from the defocus disc and directed at a randomly sampled point
@@ -211,7 +355,9 @@ impl Camera {
         } else {
             self.defocus_disc_sample()
         };
-        Ray::new(ray_origin, pixel_sample - ray_origin)
+        // Each ray samples a random instant while the shutter is open, which blurs moving objects
+        let ray_time = random_in_range(self.shutter_open, self.shutter_close);
+        Ray::new(ray_origin, pixel_sample - ray_origin, ray_time)
     }
 
     /// Returns a random point in the camera defocus disc
@@ -220,3 +366,19 @@ impl Camera {
         self.center + (p.0 * self.defocus_disc_u) + (p.1 * self.defocus_disc_v)
     }
 }
+
+/// Turns a color into the matching spectrum at the path's wavelengths, when rendering in spectral
+/// mode
+fn upsample(color: Color, wavelengths: Option<Wavelengths>) -> Color {
+    wavelengths.map_or(color, |wavelengths| wavelengths.upsample(color))
+}
+
+/// The weight given to a sample drawn with density `pdf` over one drawn with density `other_pdf`
+/// from another strategy, when combining the two with multiple importance sampling
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b <= 0.0 {
+        return 0.0;
+    }
+    a / (a + b)
+}
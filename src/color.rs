@@ -11,17 +11,59 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
     }
 }
 
-pub fn write_color(out: &mut impl Write, pixel_color: Color) {
+/// Inverse of `linear_to_gamma`, used to bring gamma encoded images (e.g textures) into linear
+/// space
+pub fn gamma_to_linear(gamma_component: f64) -> f64 {
+    if gamma_component > 0.0 {
+        gamma_component * gamma_component
+    } else {
+        0.0
+    }
+}
+
+/// The perceived brightness of a linear (Rec. 709) color
+pub fn luminance(color: Color) -> f64 {
+    0.2126 * color.0 + 0.7152 * color.1 + 0.0722 * color.2
+}
+
+/// Integer types that color channels can be quantized into
+pub trait Channel: Copy {
+    /// The value representing full intensity
+    const MAX: f64;
+
+    /// Converts an (integral) value in the range [0, MAX]
+    fn from_f64(v: f64) -> Self;
+}
+
+impl Channel for u8 {
+    const MAX: f64 = u8::MAX as f64;
+
+    fn from_f64(v: f64) -> Self {
+        v as u8
+    }
+}
+
+impl Channel for u16 {
+    const MAX: f64 = u16::MAX as f64;
+
+    fn from_f64(v: f64) -> Self {
+        v as u16
+    }
+}
+
+/// Gamma corrects the color and translates each component to a value in the range [0, T::MAX]
+pub fn quantize<T: Channel>(pixel_color: Color) -> [T; 3] {
     let gamma_space_pixel_color = pixel_color.map(linear_to_gamma);
-    // Translate each color component to a value in the RGB range [0, 255]
-    let translated_pixel_pixel_color =
-        gamma_space_pixel_color.map(|x| -> f64 { 256.0 * clamp(0.0, 0.999, x) });
-    writeln!(
-        out,
-        "{} {} {}",
-        translated_pixel_pixel_color.0 as i32,
-        translated_pixel_pixel_color.1 as i32,
-        translated_pixel_pixel_color.2 as i32
-    )
-    .expect("writing color");
+    let translated_pixel_color = gamma_space_pixel_color
+        .map(|x| -> f64 { clamp(0.0, T::MAX, ((T::MAX + 1.0) * x).floor()) });
+    [
+        T::from_f64(translated_pixel_color.0),
+        T::from_f64(translated_pixel_color.1),
+        T::from_f64(translated_pixel_color.2),
+    ]
+}
+
+pub fn write_color(out: &mut impl Write, pixel_color: Color) {
+    let [r, g, b] = quantize::<u8>(pixel_color);
+    writeln!(out, "{} {} {}", r, g, b).expect("writing color");
 }
@@ -4,7 +4,7 @@
 
 pub mod math {
     pub use std::f64::consts::PI;
-    pub use std::f64::INFINITY;
+    pub const INFINITY: f64 = f64::INFINITY;
     use std::ops::{Add, Mul};
 
     use rand::Rng;
@@ -47,17 +47,37 @@ pub mod math {
     }
 
     /// Represents a real-valued interval
-    #[derive(Default)]
+    #[derive(Copy, Clone, Default, Debug)]
     pub struct Interval {
         min: f64,
         max: f64,
     }
 
     impl Interval {
+        /// The interval containing no reals (min > max)
+        pub const EMPTY: Interval = Interval {
+            min: INFINITY,
+            max: -INFINITY,
+        };
+
         pub fn new(min: f64, max: f64) -> Self {
             Interval { min, max }
         }
 
+        /// Returns the tightest interval enclosing both `a` and `b`
+        pub fn surrounding(a: Interval, b: Interval) -> Self {
+            Interval {
+                min: a.min.min(b.min),
+                max: a.max.max(b.max),
+            }
+        }
+
+        /// Returns a copy of the interval padded by `delta / 2` on either side
+        pub fn expand(&self, delta: f64) -> Self {
+            let padding = delta / 2.0;
+            Interval::new(self.min - padding, self.max + padding)
+        }
+
         pub fn size(&self) -> f64 {
             self.max - self.min
         }
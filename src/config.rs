@@ -2,6 +2,8 @@ use std::fs;
 
 use serde::Deserialize;
 
+use crate::{material::ConductorPreset, perlin::NoisePattern};
+
 #[derive(Debug, Deserialize)]
 pub struct CameraConfig {
     pub aspect_ratio: Vec<f64>,
@@ -11,20 +13,361 @@ pub struct CameraConfig {
     pub vup: Vec<f64>,
     pub samples_per_pixel: i32,
     pub max_ray_bounces: i32,
+    #[serde(default = "default_russian_roulette_depth")]
+    pub russian_roulette_depth: i32, // Bounces after which dim paths may be randomly terminated
     pub vertical_field_of_view: f64,
     pub defocus_angle: f64,
     pub focus_distance: f64,
+    #[serde(default)]
+    pub shutter_open: f64, // Time at which the shutter opens
+    #[serde(default)]
+    pub shutter_close: f64, // Time at which the shutter closes, objects moving in between are blurred
+    #[serde(default)]
+    pub spectral: bool, // Whether to trace wavelengths rather than RGB, for dispersion
+}
+
+fn default_russian_roulette_depth() -> i32 {
+    3
 }
 
 #[derive(Debug, Deserialize)]
 pub struct OutConfig {
-    pub file: String,
+    pub file: String, // The encoder is picked based on the extension
+    #[serde(default = "default_bit_depth")]
+    pub bit_depth: u8, // Bits per channel for PNG output (8 or 16)
+    #[serde(default)]
+    pub binary_ppm: bool, // Whether PPMs use the binary (P6) rather than ASCII (P3) format
+}
+
+fn default_bit_depth() -> u8 {
+    8
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackgroundConfig {
+    Solid {
+        color: Vec<f64>,
+    },
+    Gradient {
+        bottom: Vec<f64>,
+        top: Vec<f64>,
+        #[serde(default = "default_up")]
+        up: Vec<f64>,
+    },
+    /// An equirectangular Radiance `.hdr` image
+    EnvironmentMap {
+        file: String,
+    },
+}
+
+fn default_up() -> Vec<f64> {
+    vec![0.0, 1.0, 0.0]
+}
+
+/// A named material that objects refer to by name
+#[derive(Debug, Deserialize)]
+pub struct MaterialConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: MaterialKind,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialKind {
+    Lambertian {
+        albedo: TextureConfig,
+    },
+    Metal {
+        albedo: TextureConfig,
+        fuzz: f64,
+    },
+    /// Either one of the presets or an explicit per-channel complex refractive index
+    Conductor {
+        preset: Option<ConductorPreset>,
+        eta: Option<Vec<f64>>,
+        k: Option<Vec<f64>>,
+        #[serde(default)]
+        roughness: f64,
+        film: Option<ThinFilmConfig>,
+    },
+    Dielectric {
+        refractive_index: RefractiveIndexConfig,
+        #[serde(default = "default_absorption")]
+        absorption: Vec<f64>, // Coefficient (rate per unit distance) inside the object
+        film: Option<ThinFilmConfig>,
+    },
+    RoughDielectric {
+        refractive_index: f64,
+        roughness: f64,
+        #[serde(default = "default_absorption")]
+        absorption: Vec<f64>,
+    },
+    /// The medium inside is given either by its scattering and absorption coefficients, or by its
+    /// albedo and mean free path
+    Subsurface {
+        #[serde(default = "default_ior")]
+        refractive_index: f64,
+        #[serde(default)]
+        roughness: f64,
+        scattering: Option<Vec<f64>>, // Coefficients (rates per unit distance) inside the object
+        absorption: Option<Vec<f64>>,
+        albedo: Option<Vec<f64>>, // The fraction of light scattered (rather than absorbed) at each event
+        mean_free_path: Option<Vec<f64>>, // The average distance between events
+    },
+    /// Parameters (and defaults) follow glTF's metallic-roughness model and its extensions
+    Principled {
+        #[serde(default = "default_base_color")]
+        base_color: TextureConfig,
+        #[serde(default = "default_one")]
+        metallic: f64,
+        #[serde(default = "default_one")]
+        roughness: f64,
+        #[serde(default = "default_one")]
+        specular: f64,
+        #[serde(default = "default_ior")]
+        ior: f64,
+        #[serde(default)]
+        clearcoat: f64,
+        #[serde(default)]
+        clearcoat_roughness: f64,
+        #[serde(default = "default_sheen_color")]
+        sheen_color: Vec<f64>,
+        #[serde(default)]
+        transmission: f64,
+    },
+    Isotropic {
+        albedo: TextureConfig,
+    },
+    DiffuseLight {
+        emit: Vec<f64>,
+    },
+}
+
+/// An iridescent coating, with parameters (and defaults) following glTF's iridescence extension
+#[derive(Debug, Deserialize)]
+pub struct ThinFilmConfig {
+    #[serde(default = "default_film_ior")]
+    pub ior: f64,
+    #[serde(default = "default_thickness_min")]
+    pub thickness_min: f64, // In nanometers
+    #[serde(default = "default_thickness_max")]
+    pub thickness_max: f64,
+    pub thickness: Option<TextureConfig>, // The green channel picks within the thickness range
+}
+
+fn default_film_ior() -> f64 {
+    1.3
+}
+
+fn default_thickness_min() -> f64 {
+    100.0
+}
+
+fn default_thickness_max() -> f64 {
+    400.0
+}
+
+fn default_absorption() -> Vec<f64> {
+    vec![0.0, 0.0, 0.0]
+}
+
+fn default_base_color() -> TextureConfig {
+    TextureConfig::Solid(vec![1.0, 1.0, 1.0])
+}
+
+fn default_one() -> f64 {
+    1.0
+}
+
+fn default_ior() -> f64 {
+    1.5
+}
+
+fn default_sheen_color() -> Vec<f64> {
+    vec![0.0, 0.0, 0.0]
+}
+
+/// Either a constant (e.g `refractive_index = 1.5`) or a dispersion formula table (e.g
+/// `refractive_index = { type = "cauchy", a = 1.5, b = 0.004 }`)
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RefractiveIndexConfig {
+    Constant(f64),
+    Dispersive(DispersionKind),
+}
+
+/// Wavelengths are in micrometers
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DispersionKind {
+    Cauchy { a: f64, b: f64 },
+    Sellmeier { b: Vec<f64>, c: Vec<f64> },
+}
+
+/// Either a plain color (e.g `albedo = [0.5, 0.5, 0.5]`) or a texture table (e.g `albedo = { type =
+/// "image", file = "./earth.jpg" }`)
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum TextureConfig {
+    Solid(Vec<f64>),
+    Texture(TextureKind),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextureKind {
+    Checker {
+        scale: f64,
+        even: Box<TextureConfig>,
+        odd: Box<TextureConfig>,
+    },
+    /// A PNG or JPEG image
+    Image { file: String },
+    /// A procedural Perlin noise pattern
+    Noise {
+        pattern: NoisePattern,
+        #[serde(default = "default_frequency")]
+        frequency: f64,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        #[serde(default)]
+        seed: u64, // Noise is deterministic for a given seed
+        #[serde(default = "default_low")]
+        low: Vec<f64>,
+        #[serde(default = "default_high")]
+        high: Vec<f64>,
+    },
+}
+
+fn default_frequency() -> f64 {
+    1.0
+}
+
+fn default_octaves() -> u32 {
+    7
+}
+
+fn default_low() -> Vec<f64> {
+    vec![0.0, 0.0, 0.0]
+}
+
+fn default_high() -> Vec<f64> {
+    vec![1.0, 1.0, 1.0]
+}
+
+/// An object in the scene - its shape, optionally placed through a list of transforms
+#[derive(Debug, Deserialize)]
+pub struct ObjectConfig {
+    #[serde(flatten)]
+    pub shape: ShapeConfig,
+    #[serde(default)]
+    pub transform: Vec<TransformConfig>, // Applied in the order listed
+    #[serde(default)]
+    pub keyframes: Vec<KeyframeConfig>, // Moves the object over time, applied after `transform`
+    pub density: Option<f64>, // Fills the object with a medium (e.g fog) scattering by its material
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KeyframeConfig {
+    pub time: f64,
+    pub transform: Vec<TransformConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransformConfig {
+    Translate(Vec<f64>),
+    Rotate { axis: Vec<f64>, angle: f64 }, // (in degrees) counter-clockwise about the axis
+    Scale(Vec<f64>),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShapeConfig {
+    Sphere {
+        center: Vec<f64>,
+        center_end: Option<Vec<f64>>, // Makes the sphere move from `center` at time 0 to here at time 1
+        radius: f64,
+        material: String,
+    },
+    Triangle {
+        vertices: Vec<Vec<f64>>,
+        normals: Option<Vec<Vec<f64>>>,
+        texcoords: Option<Vec<Vec<f64>>>,
+        material: String,
+    },
+    /// A parallelogram with one corner at `corner` and adjacent edges `u` and `v`
+    Quad {
+        corner: Vec<f64>,
+        u: Vec<f64>,
+        v: Vec<f64>,
+        material: String,
+    },
+    /// An infinite plane
+    Plane {
+        point: Vec<f64>,
+        normal: Vec<f64>,
+        material: String,
+    },
+    Disc {
+        center: Vec<f64>,
+        normal: Vec<f64>,
+        radius: f64,
+        material: String,
+    },
+    /// An axis-aligned box with opposite corners `min` and `max`
+    #[serde(rename = "box")]
+    Cuboid {
+        min: Vec<f64>,
+        max: Vec<f64>,
+        material: String,
+    },
+    /// A voxel grid of densities (see `GridVolume::from_raw` for the format) stretched over the
+    /// box with opposite corners `min` and `max`, scattering light according to its material
+    Volume {
+        file: String,
+        min: Vec<f64>,
+        max: Vec<f64>,
+        #[serde(default = "default_density_scale")]
+        density_scale: f64,
+        material: String,
+    },
+    /// A Wavefront OBJ file - `material` is used for faces without an `.mtl` material. Meshes
+    /// loaded from the same file (with the same material) share their geometry.
+    Mesh { file: String, material: String },
+}
+
+fn default_density_scale() -> f64 {
+    1.0
+}
+
+impl ShapeConfig {
+    /// Returns the name of the material the shape refers to
+    pub fn material(&self) -> &str {
+        match self {
+            ShapeConfig::Sphere { material, .. }
+            | ShapeConfig::Triangle { material, .. }
+            | ShapeConfig::Quad { material, .. }
+            | ShapeConfig::Plane { material, .. }
+            | ShapeConfig::Disc { material, .. }
+            | ShapeConfig::Cuboid { material, .. }
+            | ShapeConfig::Volume { material, .. }
+            | ShapeConfig::Mesh { material, .. } => material,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub camera: Option<CameraConfig>,
     pub out: Option<OutConfig>,
+    pub background: Option<BackgroundConfig>,
+    #[serde(default)]
+    pub materials: Vec<MaterialConfig>,
+    #[serde(default)]
+    pub objects: Vec<ObjectConfig>,
 }
 
 impl Config {
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    common::math::{random, Interval, INFINITY},
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// A volume of uniform density (e.g fog or smoke) filling the inside of some boundary. Rays
+/// passing through it scatter after travelling a random distance, which is shorter on average
+/// for denser media. Assumes the boundary is convex, i.e a ray enters and exits it at most once.
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hittable>,
+    negative_inverse_density: f64,
+    /// Describes how light scatters within the medium, usually `Material::Isotropic`
+    phase_function: Arc<Material>,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Hittable>, density: f64, phase_function: Arc<Material>) -> Self {
+        ConstantMedium {
+            boundary,
+            negative_inverse_density: -1.0 / density,
+            phase_function,
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        // Find where the ray enters and exits the boundary, even if that's behind the ray origin
+        // (i.e the origin is inside the medium)
+        let entry = self.boundary.hit(ray, Interval::new(-INFINITY, INFINITY))?;
+        let exit = self
+            .boundary
+            .hit(ray, Interval::new(entry.t + 0.0001, INFINITY))?;
+
+        let t_entry = entry.t.max(interval.min()).max(0.0);
+        let t_exit = exit.t.min(interval.max());
+        if t_entry >= t_exit {
+            return None;
+        }
+
+        // Sample the free-flight distance from the exponential distribution
+        let ray_length = ray.direction().length();
+        let distance_inside_boundary = (t_exit - t_entry) * ray_length;
+        let hit_distance = self.negative_inverse_density * random().ln();
+        if hit_distance > distance_inside_boundary {
+            return None;
+        }
+
+        // The normal and face are arbitrary since scattering inside a volume doesn't depend on
+        // them
+        let t = t_entry + hit_distance / ray_length;
+        Some(HitRecord::new(
+            ray,
+            t,
+            Vec3::new(1.0, 0.0, 0.0),
+            self.phase_function.clone(),
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    common::math::{random, Interval},
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    quad::Quad,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+/// An axis-aligned box made up of six quads, all facing outwards
+pub struct Cuboid {
+    sides: [Quad; 6],
+    bbox: Aabb,
+}
+
+impl Cuboid {
+    /// Constructs the box with the two points `a` and `b` at opposite corners
+    pub fn new(a: Point3, b: Point3, material: Arc<Material>) -> Self {
+        let min = a.zip_with(b, f64::min);
+        let max = a.zip_with(b, f64::max);
+
+        let dx = Vec3::new(max.0 - min.0, 0.0, 0.0);
+        let dy = Vec3::new(0.0, max.1 - min.1, 0.0);
+        let dz = Vec3::new(0.0, 0.0, max.2 - min.2);
+
+        let sides = [
+            Quad::new(Point3::new(min.0, min.1, max.2), dx, dy, material.clone()), // front
+            Quad::new(Point3::new(max.0, min.1, max.2), -dz, dy, material.clone()), // right
+            Quad::new(Point3::new(max.0, min.1, min.2), -dx, dy, material.clone()), // back
+            Quad::new(Point3::new(min.0, min.1, min.2), dz, dy, material.clone()), // left
+            Quad::new(Point3::new(min.0, max.1, max.2), dx, -dz, material.clone()), // top
+            Quad::new(Point3::new(min.0, min.1, min.2), dx, dz, material),         // bottom
+        ];
+
+        Cuboid {
+            sides,
+            bbox: Aabb::from_points(min, max),
+        }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        let mut temp_record = None;
+        let mut closest_so_far = interval.max();
+
+        for side in &self.sides {
+            if let Some(rec) = side.hit(ray, Interval::new(interval.min(), closest_so_far)) {
+                closest_so_far = rec.t;
+                temp_record = Some(rec);
+            }
+        }
+
+        temp_record
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.sides[0].is_emissive()
+    }
+
+    /// Each side is equally likely to be sampled
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        self.sides
+            .iter()
+            .map(|side| side.pdf_value(ray))
+            .sum::<f64>()
+            / 6.0
+    }
+
+    fn random(&self, origin: Point3, time: f64) -> Vec3 {
+        let side = ((random() * 6.0) as usize).min(5);
+        self.sides[side].random(origin, time)
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    common::math::{Interval, INFINITY, PI},
+    hittable::{solid_angle_pdf, HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+/// A flat, circular disc. The front face is the one that `normal` points out of.
+pub struct Disc {
+    center: Point3,
+    radius: f64,
+    normal: Vec3,
+    /// Unit vectors spanning the plane of the disc, used for the surface coordinates
+    tangent: Vec3,
+    bitangent: Vec3,
+    material: Arc<Material>,
+    bbox: Aabb,
+}
+
+impl Disc {
+    pub fn new(center: Point3, normal: Vec3, radius: f64, material: Arc<Material>) -> Self {
+        let normal = normal.into_unit();
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        // The extent of the disc along each axis shrinks as the normal aligns with that axis
+        let extent = normal.map(|n| radius * (1.0 - n * n).max(0.0).sqrt());
+        Disc {
+            center,
+            radius,
+            normal,
+            tangent,
+            bitangent,
+            material,
+            bbox: Aabb::from_points(center - extent, center + extent),
+        }
+    }
+}
+
+impl Hittable for Disc {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        let denominator = self.normal.dot(ray.direction());
+        if denominator.abs() < 1.0e-8 {
+            // The ray is parallel to the plane of the disc
+            return None;
+        }
+
+        let t = self.normal.dot(self.center - ray.origin()) / denominator;
+        if !interval.surrounds(t) {
+            return None;
+        }
+
+        let offset = ray.at(t) - self.center;
+        if offset.length_squared() > self.radius * self.radius {
+            return None;
+        }
+
+        let mut record = HitRecord::new(ray, t, self.normal, self.material.clone());
+        // Map the disc onto the unit square it is inscribed in
+        (record.u, record.v) = (
+            0.5 + offset.dot(self.tangent) / (2.0 * self.radius),
+            0.5 + offset.dot(self.bitangent) / (2.0 * self.radius),
+        );
+        Some(record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        let area = PI * self.radius * self.radius;
+        self.hit(ray, Interval::new(0.001, INFINITY))
+            .map_or(0.0, |rec| solid_angle_pdf(ray, &rec, area))
+    }
+
+    fn random(&self, origin: Point3, _time: f64) -> Vec3 {
+        let p = Vec3::in_unit_disc();
+        self.center + self.radius * (p.0 * self.tangent + p.1 * self.bitangent) - origin
+    }
+}
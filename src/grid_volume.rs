@@ -0,0 +1,158 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    aabb::Aabb,
+    common::math::{random, Interval},
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+/// A heterogeneous participating medium (e.g clouds or explosions) whose density is given by a
+/// dense voxel grid stretched over an axis-aligned box. The density between voxel centers is
+/// trilinearly interpolated.
+pub struct GridVolume {
+    bbox: Aabb,
+    min: Point3,
+    /// Size of the box along each axis
+    extent: Vec3,
+    dimensions: [usize; 3],
+    /// Densities with x varying fastest, then y, then z
+    densities: Vec<f64>,
+    /// An upper bound on the density anywhere in the volume
+    majorant: f64,
+    /// Describes how light scatters within the medium, usually `Material::Isotropic`
+    phase_function: Arc<Material>,
+}
+
+impl GridVolume {
+    pub fn new(
+        min: Point3,
+        max: Point3,
+        dimensions: [usize; 3],
+        densities: Vec<f64>,
+        phase_function: Arc<Material>,
+    ) -> Self {
+        assert_eq!(
+            densities.len(),
+            dimensions.iter().product::<usize>(),
+            "voxel count doesn't match the grid dimensions"
+        );
+        let majorant = densities.iter().cloned().fold(0.0, f64::max);
+        GridVolume {
+            bbox: Aabb::from_points(min, max),
+            min,
+            extent: max - min,
+            dimensions,
+            densities,
+            majorant,
+            phase_function,
+        }
+    }
+
+    /// Loads a grid from a raw binary file: three little-endian u32s giving the number of voxels
+    /// along x, y and z, followed by that many little-endian f32 densities (x varying fastest).
+    /// Every density is multiplied by `density_scale`.
+    pub fn from_raw(
+        path: impl AsRef<Path>,
+        min: Point3,
+        max: Point3,
+        density_scale: f64,
+        phase_function: Arc<Material>,
+    ) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+        if bytes.len() < 12 {
+            return Err(invalid("missing voxel grid header"));
+        }
+        let mut dimensions = [0; 3];
+        for (dimension, chunk) in dimensions.iter_mut().zip(bytes[..12].chunks_exact(4)) {
+            *dimension = u32::from_le_bytes(chunk.try_into().unwrap()) as usize;
+        }
+
+        let voxel_count: usize = dimensions.iter().product();
+        if voxel_count == 0 || bytes.len() != 12 + 4 * voxel_count {
+            return Err(invalid("voxel data doesn't match the grid dimensions"));
+        }
+        let densities = bytes[12..]
+            .chunks_exact(4)
+            .map(|chunk| density_scale * f32::from_le_bytes(chunk.try_into().unwrap()) as f64)
+            .collect();
+
+        Ok(GridVolume::new(
+            min,
+            max,
+            dimensions,
+            densities,
+            phase_function,
+        ))
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+        let [nx, ny, _] = self.dimensions;
+        self.densities[x + nx * (y + ny * z)]
+    }
+
+    /// Trilinearly interpolates the density at the given (world space) point
+    fn density(&self, point: Point3) -> f64 {
+        let local = (point - self.min) / self.extent;
+        let [nx, ny, nz] = self.dimensions;
+
+        // Voxel values are stored at the voxel centers
+        let lattice = |t: f64, n: usize| -> (usize, usize, f64) {
+            let x = (t * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            let i = (x.floor() as usize).min(n - 1);
+            (i, (i + 1).min(n - 1), x - i as f64)
+        };
+        let (x0, x1, fx) = lattice(local.0, nx);
+        let (y0, y1, fy) = lattice(local.1, ny);
+        let (z0, z1, fz) = lattice(local.2, nz);
+
+        let along_x =
+            |y: usize, z: usize| (1.0 - fx) * self.voxel(x0, y, z) + fx * self.voxel(x1, y, z);
+        let along_y = |z: usize| (1.0 - fy) * along_x(y0, z) + fy * along_x(y1, z);
+        (1.0 - fz) * along_y(z0) + fz * along_y(z1)
+    }
+}
+
+impl Hittable for GridVolume {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        if self.majorant <= 0.0 {
+            return None;
+        }
+        let overlap = self.bbox.overlap(ray, interval)?;
+        let ray_length = ray.direction().length();
+
+        // Delta (Woodcock) tracking: take exponentially distributed steps as if the whole volume
+        // had the majorant density, and accept each tentative collision with probability
+        // density / majorant. The rejected "null" collisions keep the estimate unbiased.
+        let mut t = overlap.min();
+        loop {
+            t -= (1.0 - random()).ln() / (self.majorant * ray_length);
+            if t >= overlap.max() {
+                return None;
+            }
+            if random() < self.density(ray.at(t)) / self.majorant {
+                // The normal and face are arbitrary since scattering inside a volume doesn't
+                // depend on them
+                return Some(HitRecord::new(
+                    ray,
+                    t,
+                    Vec3::new(1.0, 0.0, 0.0),
+                    self.phase_function.clone(),
+                ));
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
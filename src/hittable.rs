@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
+    aabb::Aabb,
     common::math::Interval,
     material::Material,
     ray::Ray,
@@ -17,9 +18,32 @@ pub struct HitRecord {
     pub material: Arc<Material>,
     pub t: f64,
     pub did_hit_front_frace: bool,
+    /// Barycentric coordinates (b1, b2) of the hit point for triangle primitives, i.e the weights
+    /// of the second and third vertices (the first vertex has weight 1 - b1 - b2)
+    pub barycentric: Option<(f64, f64)>,
+    /// Surface (texture) coordinates of the hit point
+    pub u: f64,
+    pub v: f64,
 }
 
 impl HitRecord {
+    /// Builds the record for a hit at `t` along `ray`, orienting the normal against the ray.
+    /// Assumes outward_normal is of unit length.
+    pub fn new(ray: &Ray, t: f64, outward_normal: Vec3, material: Arc<Material>) -> Self {
+        let mut record = HitRecord {
+            point: ray.at(t),
+            normal: outward_normal,
+            material,
+            t,
+            did_hit_front_frace: true,
+            barycentric: None,
+            u: 0.0,
+            v: 0.0,
+        };
+        record.set_face_normal(ray, outward_normal);
+        record
+    }
+
     /// Assumes outward_normal is of unit length
     pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: Vec3) {
         self.did_hit_front_frace = ray.direction().dot(outward_normal) < 0.0;
@@ -31,6 +55,37 @@ impl HitRecord {
     }
 }
 
+/// Converts the density of uniformly sampling a point on a surface of the given area into a density
+/// over the solid angle seen from the ray origin, at the point where the ray hits that surface
+pub fn solid_angle_pdf(ray: &Ray, rec: &HitRecord, area: f64) -> f64 {
+    let direction_length = ray.direction().length();
+    let distance_squared = (rec.t * direction_length).powi(2);
+    let cosine = (ray.direction().dot(rec.normal) / direction_length).abs();
+    distance_squared / (cosine * area)
+}
+
 pub trait Hittable: Send + Sync {
     fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord>;
+
+    /// Returns a box enclosing the entire object, used to build the BVH
+    fn bounding_box(&self) -> Aabb;
+
+    /// Whether the object emits light and can be sampled as a light source. Objects that can't
+    /// sample points on their surface (e.g infinite planes) should return false, so that their
+    /// emission is only ever picked up by rays that happen to hit them.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// The probability density (with respect to solid angle, as seen from the ray origin) with
+    /// which `random` picks the ray's direction. Zero if the ray misses the object.
+    fn pdf_value(&self, _ray: &Ray) -> f64 {
+        0.0
+    }
+
+    /// Samples a point on the surface of the object (as it is placed at the given time) and
+    /// returns the vector from `origin` to it
+    fn random(&self, _origin: Point3, _time: f64) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
 }
@@ -1,20 +1,42 @@
+mod aabb;
+mod background;
+mod bvh;
 mod camera;
 mod color;
 mod common;
 mod config;
+mod constant_medium;
+mod cuboid;
+mod disc;
+mod grid_volume;
 mod hittable;
 mod material;
+mod mesh;
+mod microfacet;
+mod output;
+mod perlin;
+mod plane;
+mod principled;
+mod quad;
 mod ray;
+mod scene;
+mod spectrum;
 mod sphere;
+mod texture;
+mod thin_film;
+mod transform;
+mod triangle;
 mod vec3;
 mod world;
 
+use background::Background;
 use camera::Camera;
 use color::Color;
 use common::math::{random, random_in_range};
-use material::Material;
+use material::{Material, RefractiveIndex};
 use sphere::Sphere;
-use std::{fs::OpenOptions, sync::Arc};
+use std::sync::Arc;
+use texture::Texture;
 use vec3::Point3;
 use world::World;
 
@@ -23,17 +45,31 @@ fn main() {
     let camera_config = config.camera.unwrap();
     let out_config = config.out.unwrap();
 
-    let mut file = OpenOptions::new()
-        .write(true)
-        .truncate(true) // Clear contents
-        .create(true)
-        .open(out_config.file)
-        .unwrap();
+    // Fall back to the classic random-sphere scene when the config doesn't describe one
+    let mut world = if config.objects.is_empty() {
+        random_spheres()
+    } else {
+        scene::build_world(&config.materials, &config.objects)
+    };
+    world.build_bvh();
 
+    let background = config
+        .background
+        .as_ref()
+        .map_or_else(Background::default, Background::new);
+
+    let camera = Camera::new(&camera_config, background);
+    let framebuffer = camera.render(&world);
+    output::write_image(&out_config, &framebuffer).expect("writing image");
+}
+
+/// The final scene from Raytracing in One Weekend: a field of small random spheres around three
+/// large ones
+fn random_spheres() -> World {
     let mut world = World::new();
 
     let ground_material = Arc::new(Material::Lambertian {
-        albedo: Color::from(0.5),
+        albedo: Arc::new(Texture::Solid(Color::from(0.5))),
     });
     world.add(Box::new(Sphere::new(
         Point3::new(0.0, -1000.0, 0.0),
@@ -49,18 +85,20 @@ fn main() {
             if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 if choose_material < 0.8 {
                     // Diffuse material
-                    let albedo = Color::random() * Color::random();
+                    let albedo = Arc::new(Texture::Solid(Color::random() * Color::random()));
                     let sphere_material = Arc::new(Material::Lambertian { albedo });
                     world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
                 } else if choose_material < 0.95 {
                     // Metal material
-                    let albedo = Color::random_in_range(0.5, 1.0);
+                    let albedo = Arc::new(Texture::Solid(Color::random_in_range(0.5, 1.0)));
                     let fuzz = random_in_range(0.0, 0.5);
                     let sphere_material = Arc::new(Material::Metal { albedo, fuzz });
                     world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
                 } else {
                     let sphere_material = Arc::new(Material::Dielectric {
-                        refractive_index: 1.5,
+                        refractive_index: RefractiveIndex::Constant(1.5),
+                        absorption: Color::from(0.0),
+                        film: None,
                     });
                     world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
                 }
@@ -69,7 +107,9 @@ fn main() {
     }
 
     let material_1 = Arc::new(Material::Dielectric {
-        refractive_index: 1.5,
+        refractive_index: RefractiveIndex::Constant(1.5),
+        absorption: Color::from(0.0),
+        film: None,
     });
     world.add(Box::new(Sphere::new(
         Point3::new(0.0, 1.0, 0.0),
@@ -78,7 +118,7 @@ fn main() {
     )));
 
     let material_2 = Arc::new(Material::Lambertian {
-        albedo: Color::new(0.4, 0.2, 0.1),
+        albedo: Arc::new(Texture::Solid(Color::new(0.4, 0.2, 0.1))),
     });
     world.add(Box::new(Sphere::new(
         Point3::new(-4.0, 1.0, 0.0),
@@ -87,7 +127,7 @@ fn main() {
     )));
 
     let material_3 = Arc::new(Material::Metal {
-        albedo: Color::new(0.7, 0.6, 0.5),
+        albedo: Arc::new(Texture::Solid(Color::new(0.7, 0.6, 0.5))),
         fuzz: 0.0,
     });
     world.add(Box::new(Sphere::new(
@@ -96,6 +136,5 @@ fn main() {
         material_3,
     )));
 
-    let camera = Camera::new(&camera_config);
-    camera.render(&world, &mut file);
+    world
 }
@@ -1,4 +1,18 @@
-use crate::{color::Color, common::math::random, hittable::HitRecord, ray::Ray, vec3::Vec3};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::{
+    color::Color,
+    common::math::{random, INFINITY, PI},
+    hittable::HitRecord,
+    microfacet::{fresnel_conductor, Frame, Ggx},
+    principled::Principled,
+    ray::Ray,
+    texture::Texture,
+    thin_film::ThinFilm,
+    vec3::Vec3,
+};
 
 /// Represents the various material options of a rendered object
 pub enum Material {
@@ -9,8 +23,9 @@ pub enum Material {
     /// containing the surface normal - though these give less realistic results.
     Lambertian {
         /// Each component is in the range [0, 1] -> this represents the fractional reflectance of
-        /// each component of light upon interaction with this material surface
-        albedo: Color,
+        /// each component of light upon interaction with this material surface. It is looked up
+        /// from a texture so that it may vary across the surface.
+        albedo: Arc<Texture>,
     },
 
     /// Shiny, shiny metals! Scattered rays are perfectly reflected about the surface normal. Also
@@ -18,23 +33,172 @@ pub enum Material {
     /// reflected ray. The length of this alteration is determined by the fuzz factor.
     Metal {
         /// Same as Lambertian
-        albedo: Color,
+        albedo: Arc<Texture>,
         /// Fuzz is in the range [0, 1] and represents that slight deviation added to the reflected
         /// ray to achieve fuzzy reflections
         fuzz: f64,
     },
 
+    /// A physically based metal. Its surface is modelled as a distribution of tiny mirror-like
+    /// facets (GGX), some of which are hidden from view or shadowed by others, each reflecting
+    /// light according to the Fresnel equations for the metal's complex refractive index.
+    Conductor {
+        /// The real part of the refractive index, per color channel
+        eta: Color,
+        /// The imaginary part (extinction coefficient), per color channel
+        k: Color,
+        /// In the range [0, 1], where 0 is a perfect mirror
+        roughness: f64,
+        /// An optional iridescent coating, e.g of oxide on tempered steel
+        film: Option<ThinFilm>,
+    },
+
     /// Materials that refract!
     Dielectric {
         /// This is really the "context-aware" refractive index of the object. Meaning that it should
         /// used as the ratio of the refractive index of the object divided by the refractive index of
         /// the enclosing medium. In most cases the enclosing medium is air (i.e refractive index of
         /// 1.0), but if you have embedded objects, you need to be careful to divide by the
-        /// appropriate refractive index.
+        /// appropriate refractive index. May vary with wavelength, which splits white light into
+        /// its colors when rendering in spectral mode.
+        refractive_index: RefractiveIndex,
+        /// The absorption coefficient of each color channel, i.e the rate (per unit distance
+        /// traveled inside the object) at which light is absorbed. A channel is dimmed by a factor
+        /// `e^(-absorption * distance)`, so thick glass is tinted more strongly than thin glass.
+        /// Zero for clear glass, with no upper limit.
+        absorption: Color,
+        /// An optional iridescent coating. Soap bubbles are a film around a dielectric with a
+        /// refractive index of 1.
+        film: Option<ThinFilm>,
+    },
+
+    /// Frosted glass and other rough, transparent materials. Like `Conductor`, the surface is made
+    /// up of GGX microfacets, each of which may either reflect or refract the incident light.
+    RoughDielectric {
+        /// The refractive index of the object relative to the enclosing medium (see `Dielectric`)
         refractive_index: f64,
+        /// In the range [0, 1], where 0 is perfectly smooth glass
+        roughness: f64,
+        /// See `Dielectric`
+        absorption: Color,
+    },
+
+    /// Translucent materials like wax, skin and marble, where light enters the object, scatters
+    /// around inside and leaves some distance away. The inside is a medium that light takes a
+    /// random walk through until it makes it back out, and the surface is a (rough) dielectric
+    /// boundary like that of `RoughDielectric`.
+    Subsurface {
+        /// The refractive index of the object relative to the enclosing medium (see `Dielectric`)
+        refractive_index: f64,
+        /// In the range [0, 1], where 0 is a perfectly smooth surface
+        roughness: f64,
+        /// The scattering coefficient (σ_s) of each color channel, i.e the rate per unit distance
+        /// traveled inside the object at which light is scattered. Not a fraction, so it may
+        /// exceed 1 - the average distance between scatters is `1 / scattering`.
+        scattering: Color,
+        /// The absorption coefficient (σ_a) of each color channel, see `Dielectric`. Light travels
+        /// `1 / (scattering + absorption)` on average before either happens, which is positive
+        /// and finite for every channel.
+        absorption: Color,
+    },
+
+    /// One material to cover most others, see `Principled`
+    Principled(Principled),
+
+    /// The phase function of participating media (e.g fog), scattering light uniformly in all
+    /// directions
+    Isotropic {
+        /// Same as Lambertian
+        albedo: Arc<Texture>,
+    },
+
+    /// Emits light rather than scattering it - any object with this material acts as an area
+    /// light
+    DiffuseLight {
+        /// The emitted radiance. Components may exceed 1 for bright lights.
+        emit: Color,
     },
 }
 
+/// Measured refractive indices of common metals, for the `Conductor` material
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConductorPreset {
+    Gold,
+    Copper,
+    Aluminium,
+    Silver,
+}
+
+impl ConductorPreset {
+    /// Returns the (eta, k) pair sampled at roughly the red, green and blue wavelengths
+    pub fn refractive_index(self) -> (Color, Color) {
+        match self {
+            ConductorPreset::Gold => (
+                Color::new(0.143, 0.374, 1.442),
+                Color::new(3.983, 2.385, 1.603),
+            ),
+            ConductorPreset::Copper => (
+                Color::new(0.200, 0.924, 1.102),
+                Color::new(3.912, 2.452, 2.142),
+            ),
+            ConductorPreset::Aluminium => (
+                Color::new(1.657, 0.880, 0.521),
+                Color::new(9.224, 6.270, 4.837),
+            ),
+            ConductorPreset::Silver => (
+                Color::new(0.155, 0.117, 0.138),
+                Color::new(4.828, 3.122, 2.147),
+            ),
+        }
+    }
+}
+
+/// The refractive index of a dielectric, optionally as a function of wavelength (dispersion)
+#[derive(Copy, Clone, Debug)]
+pub enum RefractiveIndex {
+    Constant(f64),
+    /// Cauchy's empirical equation `n = a + b / λ²`, with λ in micrometers
+    Cauchy {
+        a: f64,
+        b: f64,
+    },
+    /// The Sellmeier equation `n² = 1 + Σ bᵢλ² / (λ² - cᵢ)`, with λ in micrometers. Glass makers
+    /// publish these coefficients for their glasses.
+    Sellmeier {
+        b: [f64; 3],
+        c: [f64; 3],
+    },
+}
+
+impl RefractiveIndex {
+    /// The wavelength (in nanometers) that refractive indices are usually quoted at - the sodium D
+    /// line. Used for rays that don't carry a wavelength.
+    const REFERENCE_WAVELENGTH: f64 = 589.3;
+
+    pub fn at(self, wavelength: Option<f64>) -> f64 {
+        let micrometers = wavelength.unwrap_or(RefractiveIndex::REFERENCE_WAVELENGTH) / 1000.0;
+        let lambda_squared = micrometers * micrometers;
+        match self {
+            RefractiveIndex::Constant(n) => n,
+            RefractiveIndex::Cauchy { a, b } => a + b / lambda_squared,
+            // Coefficients fitted to a narrower range of wavelengths can make n² negative outside
+            // of it, so keep the index physical
+            RefractiveIndex::Sellmeier { b, c } => (1.0
+                + b.iter()
+                    .zip(c)
+                    .map(|(b, c)| b * lambda_squared / (lambda_squared - c))
+                    .sum::<f64>())
+            .sqrt()
+            .max(1.0),
+        }
+    }
+
+    pub fn is_dispersive(self) -> bool {
+        !matches!(self, RefractiveIndex::Constant(_))
+    }
+}
+
 /// Represents the reflected/refracted ray properties from a material interaction
 pub struct ScatterRecord {
     /// Refers to the fraction of the light energy lost as the material passes through / is
@@ -43,6 +207,20 @@ pub struct ScatterRecord {
     /// The direction vector representing the path of the incident ray after it interacts with the
     /// material surface
     pub scattered: Ray,
+    /// The density (over solid angle) with which the scattered direction was picked. Meaningless
+    /// for specular scatters.
+    pub pdf: f64,
+    /// Whether the scattered direction was entirely determined by the incident one, e.g for
+    /// mirrors and glass. Such bounces can't be lit by sampling the lights directly.
+    pub is_specular: bool,
+}
+
+/// The outcome of light traveling through the inside of an object, see `Material::traverse`
+pub enum Traversal {
+    /// The light made it through, scaled by the given factor
+    Transmitted(Color),
+    /// The light was scattered by a medium inside the object before making it through
+    Scattered(ScatterRecord),
 }
 
 impl Material {
@@ -54,56 +232,441 @@ impl Material {
                 if scatter_direction.is_near_zero() {
                     scatter_direction = rec.normal;
                 }
-                let attenuation = albedo;
-                let scattered = Ray::new(rec.point, scatter_direction);
+                let attenuation = albedo.value(rec.u, rec.v, rec.point);
+                let scattered = Ray::new(rec.point, scatter_direction, ray.time());
                 Some(ScatterRecord {
-                    attenuation: *attenuation,
+                    attenuation,
                     scattered,
+                    pdf: self.pdf(ray, rec, scatter_direction),
+                    is_specular: false,
                 })
             }
             Material::Metal { albedo, fuzz } => {
                 let reflected = ray.direction().into_unit().reflect(rec.normal).into_unit()
                     + *fuzz * Vec3::on_unit_sphere();
-                let attenuation = albedo;
-                let scattered = Ray::new(rec.point, reflected);
+                let attenuation = albedo.value(rec.u, rec.v, rec.point);
+                let scattered = Ray::new(rec.point, reflected, ray.time());
                 Some(ScatterRecord {
-                    attenuation: *attenuation,
+                    attenuation,
                     scattered,
+                    pdf: self.pdf(ray, rec, reflected),
+                    is_specular: *fuzz <= 0.0,
                 })
                 .filter(|_| scattered.direction().dot(rec.normal) > 0.0)
             }
-            Material::Dielectric { refractive_index } => {
-                let attenuation = Color::from(1.0);
+            Material::Conductor {
+                eta,
+                k,
+                roughness,
+                film,
+            } => {
+                let frame = Frame::new(rec.normal);
+                let wo = frame.to_local(-ray.direction().into_unit());
+                if wo.2 <= 0.0 {
+                    return None;
+                }
+
+                let ggx = Ggx::new(*roughness);
+                if ggx.is_smooth() {
+                    let direction = ray.direction().into_unit().reflect(rec.normal);
+                    return Some(ScatterRecord {
+                        attenuation: conductor_reflectance(film, ray, rec, wo.2, *eta, *k),
+                        scattered: Ray::new(rec.point, direction, ray.time()),
+                        pdf: 0.0,
+                        is_specular: true,
+                    });
+                }
+
+                // Reflect off a facet picked among those visible from the incident direction
+                let m = ggx.sample_visible_normal(wo);
+                let wi = (-wo).reflect(m);
+                if wi.2 <= 0.0 {
+                    return None;
+                }
+                let direction = frame.to_world(wi);
+                Some(ScatterRecord {
+                    attenuation: conductor_reflectance(film, ray, rec, wo.dot(m), *eta, *k)
+                        * (ggx.g(wo, wi) / ggx.g1(wo)),
+                    scattered: Ray::new(rec.point, direction, ray.time()),
+                    pdf: self.pdf(ray, rec, direction),
+                    is_specular: false,
+                })
+            }
+            Material::Dielectric {
+                refractive_index,
+                film,
+                ..
+            } => {
+                let refractive_index = refractive_index.at(ray.wavelength());
 
                 let (etai, etat) = if rec.did_hit_front_frace {
                     // The ray is going from the environment _into_ this object
-                    (1.0, *refractive_index)
+                    (1.0, refractive_index)
                 } else {
                     // The ray is emerging from _within_ the object into the environment
-                    (*refractive_index, 1.0)
+                    (refractive_index, 1.0)
                 };
 
                 let cos_theta = -ray.direction().into_unit().dot(rec.normal).min(1.0);
                 let etai_over_etat = etai / etat;
                 let sin_theta = (1.0 - cos_theta.powi(2)).sqrt();
                 let can_refract = etai_over_etat * sin_theta <= 1.0;
-                let direction = if can_refract && schlick(*refractive_index, cos_theta) <= random()
-                {
-                    ray.direction()
-                        .into_unit()
-                        .refract(rec.normal.into_unit(), etai, etat)
+
+                // A coated surface reflects each color channel by a different amount, so pick
+                // between reflection and refraction by the average and reweigh each channel to
+                // make up for it
+                let reflectance = film.as_ref().map(|film| {
+                    film.reflectance_dielectric(rec, cos_theta, refractive_index, ray.wavelength())
+                });
+                let reflect_probability = reflectance.map_or_else(
+                    || schlick(refractive_index, cos_theta),
+                    |reflectance| (reflectance.0 + reflectance.1 + reflectance.2) / 3.0,
+                );
+                let (direction, attenuation) = if can_refract && reflect_probability <= random() {
+                    let direction =
+                        ray.direction()
+                            .into_unit()
+                            .refract(rec.normal.into_unit(), etai, etat);
+                    let attenuation = reflectance.map_or(Color::from(1.0), |reflectance| {
+                        (Color::from(1.0) - reflectance) / (1.0 - reflect_probability)
+                    });
+                    (direction, attenuation)
                 } else {
-                    ray.direction().into_unit().reflect(rec.normal)
+                    let direction = ray.direction().into_unit().reflect(rec.normal);
+                    let attenuation = reflectance
+                        .filter(|_| can_refract)
+                        .map_or(Color::from(1.0), |reflectance| {
+                            reflectance / reflect_probability
+                        });
+                    (direction, attenuation)
                 };
 
-                let scattered = Ray::new(rec.point, direction);
+                let scattered = Ray::new(rec.point, direction, ray.time());
                 Some(ScatterRecord {
                     attenuation,
                     scattered,
+                    pdf: 0.0,
+                    is_specular: true,
+                })
+            }
+            Material::RoughDielectric {
+                refractive_index,
+                roughness,
+                ..
+            }
+            | Material::Subsurface {
+                refractive_index,
+                roughness,
+                ..
+            } => {
+                let frame = Frame::new(rec.normal);
+                let wo = frame.to_local(-ray.direction().into_unit());
+                let ggx = Ggx::new(*roughness);
+                let (wi, weight) =
+                    ggx.sample_dielectric(wo, relative_refractive_index(*refractive_index, rec))?;
+                let direction = frame.to_world(wi);
+                Some(ScatterRecord {
+                    attenuation: Color::from(weight),
+                    scattered: Ray::new(rec.point, direction, ray.time()),
+                    pdf: self.pdf(ray, rec, direction),
+                    is_specular: ggx.is_smooth(),
                 })
             }
+            Material::Principled(principled) => principled.scatter(ray, rec),
+            Material::Isotropic { albedo } => Some(ScatterRecord {
+                attenuation: albedo.value(rec.u, rec.v, rec.point),
+                scattered: Ray::new(rec.point, Vec3::on_unit_sphere(), ray.time()),
+                pdf: 1.0 / (4.0 * PI),
+                is_specular: false,
+            }),
+            Material::DiffuseLight { .. } => None,
         }
     }
+
+    /// The fraction of the light arriving from `direction` that is scattered back along the
+    /// incident ray, i.e the BSDF times the cosine of the angle between `direction` and the
+    /// normal. For non-specular scatters `attenuation` is this divided by `pdf` in the scattered
+    /// direction. Always black for specular materials, which scatter in a single direction.
+    pub fn eval(&self, ray: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        match self {
+            Material::Lambertian { albedo } | Material::Metal { albedo, .. } => {
+                if direction.dot(rec.normal) <= 0.0 {
+                    return Color::from(0.0);
+                }
+                // Every direction above the surface is scattered in proportion to how likely it
+                // is to be sampled
+                albedo.value(rec.u, rec.v, rec.point) * self.pdf(ray, rec, direction)
+            }
+            Material::Conductor {
+                eta,
+                k,
+                roughness,
+                film,
+            } => {
+                let ggx = Ggx::new(*roughness);
+                let frame = Frame::new(rec.normal);
+                let wo = frame.to_local(-ray.direction().into_unit());
+                let wi = frame.to_local(direction.into_unit());
+                if ggx.is_smooth() || wo.2 <= 0.0 || wi.2 <= 0.0 {
+                    return Color::from(0.0);
+                }
+                let m = (wo + wi).into_unit();
+                conductor_reflectance(film, ray, rec, wo.dot(m), *eta, *k)
+                    * (ggx.d(m) * ggx.g(wo, wi) / (4.0 * wo.2))
+            }
+            Material::RoughDielectric {
+                refractive_index,
+                roughness,
+                ..
+            }
+            | Material::Subsurface {
+                refractive_index,
+                roughness,
+                ..
+            } => {
+                let frame = Frame::new(rec.normal);
+                let wo = frame.to_local(-ray.direction().into_unit());
+                let wi = frame.to_local(direction.into_unit());
+                let eta = relative_refractive_index(*refractive_index, rec);
+                Ggx::new(*roughness)
+                    .eval_dielectric(wo, wi, eta)
+                    .map_or(Color::from(0.0), |(value, _)| Color::from(value))
+            }
+            Material::Principled(principled) => principled.eval(ray, rec, direction),
+            Material::Isotropic { albedo } => {
+                albedo.value(rec.u, rec.v, rec.point) * self.pdf(ray, rec, direction)
+            }
+            _ => Color::from(0.0),
+        }
+    }
+
+    /// The density (over solid angle) with which `scatter` picks the given direction
+    pub fn pdf(&self, ray: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        match self {
+            Material::Lambertian { .. } => {
+                let cosine = rec.normal.dot(direction.into_unit());
+                cosine.max(0.0) / PI
+            }
+            Material::Metal { fuzz, .. } if *fuzz > 0.0 => {
+                let reflected = ray.direction().into_unit().reflect(rec.normal).into_unit();
+                fuzzed_reflection_pdf(reflected, *fuzz, direction)
+            }
+            Material::Conductor { roughness, .. } => {
+                let ggx = Ggx::new(*roughness);
+                let frame = Frame::new(rec.normal);
+                let wo = frame.to_local(-ray.direction().into_unit());
+                let wi = frame.to_local(direction.into_unit());
+                if ggx.is_smooth() || wi.2 <= 0.0 {
+                    return 0.0;
+                }
+                // Reflection doubles the angle between the facet normal and the direction,
+                // which spreads the facet density over four times the solid angle
+                let m = (wo + wi).into_unit();
+                ggx.visible_normal_pdf(wo, m) / (4.0 * wo.dot(m))
+            }
+            Material::RoughDielectric {
+                refractive_index,
+                roughness,
+                ..
+            }
+            | Material::Subsurface {
+                refractive_index,
+                roughness,
+                ..
+            } => {
+                let frame = Frame::new(rec.normal);
+                let wo = frame.to_local(-ray.direction().into_unit());
+                let wi = frame.to_local(direction.into_unit());
+                let eta = relative_refractive_index(*refractive_index, rec);
+                Ggx::new(*roughness)
+                    .eval_dielectric(wo, wi, eta)
+                    .map_or(0.0, |(_, pdf)| pdf)
+            }
+            Material::Principled(principled) => principled.pdf(ray, rec, direction),
+            Material::Isotropic { .. } => 1.0 / (4.0 * PI),
+            _ => 0.0,
+        }
+    }
+
+    /// Whether light refracted into an object made of this material travels through a medium
+    /// inside it, see `traverse`
+    pub fn encloses_medium(&self) -> bool {
+        matches!(
+            self,
+            Material::Dielectric { .. }
+                | Material::RoughDielectric { .. }
+                | Material::Subsurface { .. }
+        )
+    }
+
+    /// What happens to light traveling `distance` along `ray` through the inside of an object
+    /// made of this material (which may be infinite if the ray never leaves it). Clear objects
+    /// only dim it, following the Beer–Lambert law. `throughput` is the fraction of each channel
+    /// of the light that makes it back to the camera, and steers sampling towards the channels
+    /// that matter the most.
+    pub fn traverse(&self, ray: &Ray, distance: f64, throughput: Color) -> Traversal {
+        match self {
+            Material::Dielectric { absorption, .. }
+            | Material::RoughDielectric { absorption, .. } => {
+                Traversal::Transmitted(beer_lambert(*absorption, distance))
+            }
+            Material::Subsurface {
+                scattering,
+                absorption,
+                ..
+            } => random_walk_step(ray, distance, *scattering, *absorption, throughput),
+            _ => Traversal::Transmitted(Color::from(1.0)),
+        }
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        matches!(self, Material::DiffuseLight { .. })
+    }
+
+    /// Whether the material treats each wavelength differently in a way that can only be traced
+    /// one wavelength at a time, i.e by scattering them in different directions or by
+    /// thin-film interference
+    pub fn is_dispersive(&self) -> bool {
+        match self {
+            Material::Dielectric {
+                refractive_index,
+                film,
+                ..
+            } => refractive_index.is_dispersive() || film.is_some(),
+            Material::Conductor { film, .. } => film.is_some(),
+            _ => false,
+        }
+    }
+
+    /// The light emitted from the surface at the hit point. Black for everything but lights.
+    pub fn emitted(&self, _rec: &HitRecord) -> Color {
+        match self {
+            Material::DiffuseLight { emit } => *emit,
+            _ => Color::from(0.0),
+        }
+    }
+}
+
+/// Samples how far light travels through a scattering medium before it scatters, if it does so
+/// before covering `distance`. Each color channel has a distance distribution of its own, so one
+/// is picked (in proportion to `throughput`) and the result is weighed by their combined
+/// density. Picking by throughput keeps the weights from compounding over long walks where one
+/// channel scatters far more often than the others.
+fn random_walk_step(
+    ray: &Ray,
+    distance: f64,
+    scattering: Color,
+    absorption: Color,
+    throughput: Color,
+) -> Traversal {
+    let extinction = scattering + absorption;
+    let total = throughput.0 + throughput.1 + throughput.2;
+    let probabilities = if total > 0.0 {
+        throughput / total
+    } else {
+        Color::from(1.0 / 3.0)
+    };
+    let choice = random();
+    let sigma = if choice < probabilities.0 {
+        extinction.0
+    } else if choice < probabilities.0 + probabilities.1 {
+        extinction.1
+    } else {
+        extinction.2
+    };
+    let free_flight = if sigma > 0.0 {
+        -(1.0 - random()).ln() / sigma
+    } else {
+        INFINITY
+    };
+    let expected = |color: Color| {
+        let weighted = probabilities * color;
+        weighted.0 + weighted.1 + weighted.2
+    };
+
+    if free_flight >= distance {
+        let transmittance = beer_lambert(extinction, distance);
+        return Traversal::Transmitted(transmittance / expected(transmittance));
+    }
+
+    // Scatter uniformly in all directions, like `Isotropic`
+    let transmittance = beer_lambert(extinction, free_flight);
+    let point = ray.at(free_flight / ray.direction().length());
+    Traversal::Scattered(ScatterRecord {
+        attenuation: scattering * transmittance / expected(extinction * transmittance),
+        scattered: Ray::new(point, Vec3::on_unit_sphere(), ray.time()),
+        pdf: 1.0 / (4.0 * PI),
+        is_specular: false,
+    })
+}
+
+/// The fraction of each channel of light that makes it `distance` through a medium absorbing
+/// (or scattering) it at the rates `sigma`. Media that don't interact with a channel pass all of
+/// it, even over an infinite distance.
+fn beer_lambert(sigma: Color, distance: f64) -> Color {
+    sigma.map(|sigma| {
+        if sigma > 0.0 {
+            (-sigma * distance).exp()
+        } else {
+            1.0
+        }
+    })
+}
+
+/// The refractive index on the far side of the surface relative to the side the ray arrived from
+fn relative_refractive_index(refractive_index: f64, rec: &HitRecord) -> f64 {
+    if rec.did_hit_front_frace {
+        refractive_index
+    } else {
+        1.0 / refractive_index
+    }
+}
+
+/// The reflectance of a conductor, optionally coated by a thin film
+fn conductor_reflectance(
+    film: &Option<ThinFilm>,
+    ray: &Ray,
+    rec: &HitRecord,
+    cos_theta: f64,
+    eta: Color,
+    k: Color,
+) -> Color {
+    match film {
+        Some(film) => film.reflectance_conductor(rec, cos_theta, eta, k, ray.wavelength()),
+        None => fresnel_conductor_color(cos_theta, eta, k),
+    }
+}
+
+/// Applies the Fresnel equations for conductors to each color channel
+fn fresnel_conductor_color(cos_theta: f64, eta: Color, k: Color) -> Color {
+    Color::new(
+        fresnel_conductor(cos_theta, eta.0, k.0),
+        fresnel_conductor(cos_theta, eta.1, k.1),
+        fresnel_conductor(cos_theta, eta.2, k.2),
+    )
+}
+
+/// Fuzzy reflections aim at a uniformly random point on the sphere of radius `fuzz` around the tip
+/// of the (unit) mirror direction. Returns that density converted to solid angle, summed over
+/// each point at which `direction` crosses the sphere.
+fn fuzzed_reflection_pdf(reflected: Vec3, fuzz: f64, direction: Vec3) -> f64 {
+    let direction = direction.into_unit();
+    let h = direction.dot(reflected);
+    let discriminant = h * h - 1.0 + fuzz * fuzz;
+    if discriminant <= 0.0 {
+        return 0.0;
+    }
+
+    let sqrt_d = discriminant.sqrt();
+    let area = 4.0 * PI * fuzz * fuzz;
+    [h - sqrt_d, h + sqrt_d]
+        .into_iter()
+        .filter(|&t| t > 0.0)
+        .map(|t| {
+            let normal = (t * direction - reflected) / fuzz;
+            t * t / (direction.dot(normal).abs() * area)
+        })
+        .sum()
 }
 
 /// Schlick's approximation for computing whether an incident ray reflects or refracts at a material surface
@@ -113,3 +676,18 @@ fn schlick(refractive_index: f64, cos_theta: f64) -> f64 {
     let r0 = ((1.0 - refractive_index) / (1.0 + refractive_index)).powi(2);
     r0 + (1.0 - r0) * (1.0 - cos_theta).powi(5)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sellmeier_matches_published_index_of_bk7() {
+        let bk7 = RefractiveIndex::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        };
+        // Schott quotes n_d = 1.5168 at the helium d line
+        assert!((bk7.at(Some(587.6)) - 1.5168).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{path::Path, sync::Arc};
+
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    common::math::Interval,
+    hittable::{HitRecord, Hittable},
+    material::{Material, RefractiveIndex},
+    ray::Ray,
+    texture::Texture,
+    triangle::Triangle,
+    vec3::{Point3, Vec3},
+    world::World,
+};
+
+/// A triangle mesh loaded from a Wavefront OBJ file. The triangles are kept in their own BVH so
+/// that the mesh can be added to the world as a single object.
+pub struct Mesh {
+    triangles: World,
+}
+
+impl Mesh {
+    /// Loads every model in the OBJ file at `path` (polygons are fan-triangulated). Materials from
+    /// any referenced `.mtl` libraries are mapped onto our `Material`s; faces without a material
+    /// (or whose material library can't be loaded) use `default_material`.
+    pub fn from_obj(
+        path: impl AsRef<Path>,
+        default_material: Arc<Material>,
+    ) -> Result<Self, tobj::LoadError> {
+        let path = path.as_ref();
+        // Texture maps are relative to the OBJ file
+        let directory = path.parent().unwrap_or(Path::new("."));
+        let (models, mtl_materials) = tobj::load_obj(
+            path,
+            &tobj::LoadOptions {
+                single_index: true,
+                triangulate: true,
+                ignore_points: true,
+                ignore_lines: true,
+            },
+        )?;
+
+        let materials: Vec<Arc<Material>> = match mtl_materials {
+            Ok(mtl_materials) => mtl_materials
+                .iter()
+                .map(|mtl| Arc::new(material_from_mtl(mtl, directory)))
+                .collect(),
+            Err(err) => {
+                eprintln!(
+                    "Failed to load materials for {}: {}, using the default material",
+                    path.display(),
+                    err
+                );
+                Vec::new()
+            }
+        };
+
+        let mut triangles = World::new();
+        for model in models {
+            let mesh = &model.mesh;
+            let material = mesh
+                .material_id
+                .and_then(|id| materials.get(id))
+                .unwrap_or(&default_material);
+
+            let position = |i: usize| {
+                Point3::new(
+                    mesh.positions[3 * i],
+                    mesh.positions[3 * i + 1],
+                    mesh.positions[3 * i + 2],
+                )
+            };
+            let normal = |i: usize| {
+                Vec3::new(
+                    mesh.normals[3 * i],
+                    mesh.normals[3 * i + 1],
+                    mesh.normals[3 * i + 2],
+                )
+            };
+            let texcoord = |i: usize| (mesh.texcoords[2 * i], mesh.texcoords[2 * i + 1]);
+
+            for face in mesh.indices.chunks_exact(3) {
+                let face = [face[0] as usize, face[1] as usize, face[2] as usize];
+                let mut triangle = Triangle::new(face.map(position), material.clone());
+                if !mesh.normals.is_empty() {
+                    triangle = triangle.with_normals(face.map(normal));
+                }
+                if !mesh.texcoords.is_empty() {
+                    triangle = triangle.with_texcoords(face.map(texcoord));
+                }
+                triangles.add(Box::new(triangle));
+            }
+        }
+        triangles.build_bvh();
+
+        Ok(Mesh { triangles })
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        self.triangles.hit(ray, interval)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.triangles.bounding_box()
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.triangles.is_emissive()
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        self.triangles.pdf_value(ray)
+    }
+
+    fn random(&self, origin: Point3, time: f64) -> Vec3 {
+        self.triangles.random(origin, time)
+    }
+}
+
+/// Maps a `.mtl` material onto the closest matching `Material`, based on its emission,
+/// illumination model and transparency
+fn material_from_mtl(mtl: &tobj::Material, directory: &Path) -> Material {
+    let to_color = |c: [f64; 3]| Color::new(c[0], c[1], c[2]);
+    let diffuse = mtl.diffuse.map(to_color).unwrap_or(Color::from(0.8));
+    let diffuse_texture = mtl
+        .diffuse_texture
+        .as_ref()
+        .and_then(|file| match Texture::from_image(directory.join(file)) {
+            Ok(texture) => Some(texture),
+            Err(err) => {
+                eprintln!(
+                    "Failed to load texture {}: {}, using the diffuse color",
+                    file, err
+                );
+                None
+            }
+        })
+        .unwrap_or(Texture::Solid(diffuse));
+    let illumination_model = mtl.illumination_model.unwrap_or(2);
+
+    if let Some(emit) = mtl.emissive.map(to_color).filter(|e| !e.is_near_zero()) {
+        Material::DiffuseLight { emit }
+    } else if mtl.dissolve.is_some_and(|d| d < 1.0) || matches!(illumination_model, 4 | 6 | 7 | 9) {
+        // Transparent / refractive
+        Material::Dielectric {
+            refractive_index: RefractiveIndex::Constant(mtl.optical_density.unwrap_or(1.5)),
+            absorption: Color::from(0.0),
+            film: None,
+        }
+    } else if matches!(illumination_model, 3 | 5 | 8) {
+        // Reflective - the Phong exponent is mapped onto an (approximate) roughness
+        let shininess = mtl.shininess.unwrap_or(0.0).max(0.0);
+        Material::Metal {
+            albedo: Arc::new(
+                mtl.specular
+                    .map(to_color)
+                    .map_or(diffuse_texture, Texture::Solid),
+            ),
+            fuzz: (2.0 / (shininess + 2.0)).sqrt().min(1.0),
+        }
+    } else {
+        Material::Lambertian {
+            albedo: Arc::new(diffuse_texture),
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{
+    common::math::{random, PI},
+    vec3::Vec3,
+};
+
+/// An orthonormal basis around a surface normal. Microfacet models are much simpler to evaluate
+/// in this "shading space", where the normal is +Z.
+pub struct Frame {
+    tangent: Vec3,
+    bitangent: Vec3,
+    normal: Vec3,
+}
+
+impl Frame {
+    /// Assumes `normal` is of unit length
+    pub fn new(normal: Vec3) -> Self {
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        Frame {
+            tangent,
+            bitangent,
+            normal,
+        }
+    }
+
+    pub fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            v.dot(self.tangent),
+            v.dot(self.bitangent),
+            v.dot(self.normal),
+        )
+    }
+
+    pub fn to_world(&self, v: Vec3) -> Vec3 {
+        v.0 * self.tangent + v.1 * self.bitangent + v.2 * self.normal
+    }
+}
+
+/// The GGX (Trowbridge-Reitz) distribution of microfacet normals, which models rough surfaces as
+/// tiny mirror-like facets. All directions are in shading space.
+pub struct Ggx {
+    alpha: f64,
+}
+
+impl Ggx {
+    /// Below this the surface is treated as perfectly smooth, since the distribution degenerates
+    /// into a spike that can't be sampled reliably
+    const MIN_ALPHA: f64 = 1.0e-3;
+
+    /// `roughness` is in the range [0, 1], and is squared into the distribution's width so that
+    /// it changes roughly linearly in appearance
+    pub fn new(roughness: f64) -> Self {
+        Ggx {
+            alpha: roughness * roughness,
+        }
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        self.alpha < Ggx::MIN_ALPHA
+    }
+
+    /// The density of microfacets (per unit area of the surface) facing along `m`
+    pub fn d(&self, m: Vec3) -> f64 {
+        if m.2 <= 0.0 {
+            return 0.0;
+        }
+        let alpha_squared = self.alpha * self.alpha;
+        let denominator = m.2 * m.2 * (alpha_squared - 1.0) + 1.0;
+        alpha_squared / (PI * denominator * denominator)
+    }
+
+    /// Smith's auxiliary function - the area of the facets hidden from direction `w` relative to
+    /// the visible ones
+    fn lambda(&self, w: Vec3) -> f64 {
+        let cos_squared = w.2 * w.2;
+        if cos_squared <= 0.0 {
+            return INFINITE_LAMBDA;
+        }
+        let tan_squared = (1.0 - cos_squared).max(0.0) / cos_squared;
+        0.5 * ((1.0 + self.alpha * self.alpha * tan_squared).sqrt() - 1.0)
+    }
+
+    /// The fraction of facets visible from direction `w`
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// The (height-correlated Smith) fraction of facets visible from both `wo` and `wi`
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Samples a facet normal in proportion to how much of it is visible from `wo` (Heitz 2018,
+    /// "Sampling the GGX Distribution of Visible Normals"), which avoids picking facets that face
+    /// away from the viewer
+    pub fn sample_visible_normal(&self, wo: Vec3) -> Vec3 {
+        // Stretch the view direction so the distribution becomes a hemisphere...
+        let vh = Vec3::new(self.alpha * wo.0, self.alpha * wo.1, wo.2).into_unit();
+        let length_squared = vh.0 * vh.0 + vh.1 * vh.1;
+        let t1 = if length_squared > 0.0 {
+            Vec3::new(-vh.1, vh.0, 0.0) / length_squared.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(&t1);
+
+        // ...sample the part of its projected disc visible from that direction...
+        let r = random().sqrt();
+        let phi = 2.0 * PI * random();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.2);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        // ...and unstretch the normal found
+        Vec3::new(self.alpha * nh.0, self.alpha * nh.1, nh.2.max(1.0e-6)).into_unit()
+    }
+
+    /// The density with which `sample_visible_normal` picks `m`
+    pub fn visible_normal_pdf(&self, wo: Vec3, m: Vec3) -> f64 {
+        if wo.2 <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * wo.dot(m).max(0.0) * self.d(m) / wo.2
+    }
+
+    /// Samples the direction that light arriving along `wo` is scattered into by a rough
+    /// dielectric boundary with relative refractive index `eta`, picking between reflection and
+    /// refraction in proportion to how much light each carries. Returns that direction along with
+    /// its weight (the BSDF times the cosine over the density). A smooth boundary acts as a single
+    /// facet aligned with the normal.
+    pub fn sample_dielectric(&self, wo: Vec3, eta: f64) -> Option<(Vec3, f64)> {
+        let m = if self.is_smooth() {
+            Vec3::new(0.0, 0.0, 1.0)
+        } else {
+            self.sample_visible_normal(wo)
+        };
+        let reflectance = fresnel_dielectric(wo.dot(m), eta);
+
+        // The Fresnel term cancels out of the weight
+        let (wi, mut weight) = if random() < reflectance {
+            ((-wo).reflect(m), 1.0)
+        } else {
+            // Radiance is compressed into a smaller solid angle when entering a denser medium
+            // (and spread out when leaving it)
+            (refract(wo, m, eta)?, 1.0 / (eta * eta))
+        };
+        let is_reflection = wi.2 > 0.0;
+        if is_reflection != (wo.dot(m) * wi.dot(m) > 0.0) {
+            // The facet sent the ray to the wrong side of the surface
+            return None;
+        }
+
+        if !self.is_smooth() {
+            weight *= self.g(wo, wi) / self.g1(wo);
+        }
+        Some((wi, weight))
+    }
+
+    /// The BSDF times the cosine for a rough dielectric boundary with relative refractive index
+    /// `eta` scattering light arriving along `wo` into `wi`, along with the density with which
+    /// `sample_dielectric` picks `wi`. None for smooth boundaries, which scatter into single
+    /// directions.
+    pub fn eval_dielectric(&self, wo: Vec3, wi: Vec3, eta: f64) -> Option<(f64, f64)> {
+        let (m, is_reflection) = half_vector(wo, wi, eta).filter(|_| !self.is_smooth())?;
+
+        let reflectance = fresnel_dielectric(wo.dot(m), eta);
+        let facets = self.d(m) * self.g(wo, wi) / wo.2;
+        let facet_pdf = self.visible_normal_pdf(wo, m);
+        if is_reflection {
+            Some((
+                reflectance * facets / 4.0,
+                reflectance * facet_pdf / (4.0 * wo.dot(m)),
+            ))
+        } else {
+            // The change of variables from facet normals to refracted directions
+            let jacobian = wi.dot(m).abs() / (wi.dot(m) + wo.dot(m) / eta).powi(2);
+            let transmittance = 1.0 - reflectance;
+            Some((
+                transmittance / (eta * eta) * facets * wo.dot(m) * jacobian,
+                transmittance * facet_pdf * jacobian,
+            ))
+        }
+    }
+}
+
+/// Finds the facet normal that scatters `wo` into `wi` through a dielectric boundary with
+/// relative refractive index `eta`, and whether it does so by reflection. None when no facet
+/// facing `wo` could do so.
+fn half_vector(wo: Vec3, wi: Vec3, eta: f64) -> Option<(Vec3, bool)> {
+    if wo.2 <= 0.0 || wi.2 == 0.0 {
+        return None;
+    }
+    let is_reflection = wi.2 > 0.0;
+    let m = if is_reflection {
+        wo + wi
+    } else {
+        wo + eta * wi
+    };
+    if m.is_near_zero() {
+        return None;
+    }
+    let m = if m.2 < 0.0 { -m } else { m }.into_unit();
+
+    // Reflections stay on the same side of the facet, refractions cross it
+    let crosses = wo.dot(m) * wi.dot(m) < 0.0;
+    (wo.dot(m) > 0.0 && crosses != is_reflection).then_some((m, is_reflection))
+}
+
+/// Stands in for Smith's lambda at grazing angles, where every facet is hidden
+const INFINITE_LAMBDA: f64 = 1.0e12;
+
+/// The fraction of light reflected off a conductor with the complex refractive index `eta + ik`,
+/// for light arriving at an angle with cosine `cos_theta` to the normal
+pub fn fresnel_conductor(cos_theta: f64, eta: f64, k: f64) -> f64 {
+    let cos_squared = cos_theta.clamp(0.0, 1.0).powi(2);
+    let sin_squared = 1.0 - cos_squared;
+    let (eta_squared, k_squared) = (eta * eta, k * k);
+
+    let t0 = eta_squared - k_squared - sin_squared;
+    let a_squared_plus_b_squared = (t0 * t0 + 4.0 * eta_squared * k_squared).sqrt();
+    let t1 = a_squared_plus_b_squared + cos_squared;
+    let a = (0.5 * (a_squared_plus_b_squared + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_theta * a;
+    let r_s = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos_squared * a_squared_plus_b_squared + sin_squared * sin_squared;
+    let t4 = t2 * sin_squared;
+    let r_p = r_s * (t3 - t4) / (t3 + t4);
+
+    0.5 * (r_s + r_p)
+}
+
+/// The fraction of light reflected at the boundary between two dielectrics, for light arriving at
+/// an angle with cosine `cos_theta` to the normal. `eta` is the refractive index of the far side
+/// relative to that of the side the light arrives from.
+pub fn fresnel_dielectric(cos_theta: f64, eta: f64) -> f64 {
+    let cos_i = cos_theta.clamp(0.0, 1.0);
+    let sin_squared_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin_squared_t >= 1.0 {
+        // Total internal reflection
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin_squared_t).sqrt();
+
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+/// Refracts the direction `wo` (pointing away from the surface, on the same side as `normal`)
+/// through a boundary with relative refractive index `eta`, returning the transmitted direction
+/// (pointing away on the far side). None on total internal reflection.
+fn refract(wo: Vec3, normal: Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = wo.dot(normal);
+    let sin_squared_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin_squared_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin_squared_t).sqrt();
+    Some(-wo / eta + (cos_i / eta - cos_t) * normal)
+}
@@ -0,0 +1,172 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use image::{DynamicImage, ImageBuffer, ImageFormat};
+
+use crate::{
+    color::{quantize, write_color, Channel, Color},
+    config::OutConfig,
+};
+
+/// The rendered image - one (linear) color per pixel, stored row by row starting at the top left
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Color::from(0.0); width * height],
+        }
+    }
+
+    /// Returns the pixels of row `j` for writing
+    pub fn row_mut(&mut self, j: usize) -> &mut [Color] {
+        &mut self.pixels[j * self.width..(j + 1) * self.width]
+    }
+
+    /// Returns the linear channels of every pixel, flattened into a single buffer
+    fn linear(&self) -> Vec<f32> {
+        self.pixels
+            .iter()
+            .flat_map(|pixel_color| [pixel_color.0, pixel_color.1, pixel_color.2])
+            .map(|channel| channel as f32)
+            .collect()
+    }
+
+    /// Returns the gamma corrected channels of every pixel, flattened into a single buffer
+    fn quantized<T: Channel>(&self) -> Vec<T> {
+        self.pixels
+            .iter()
+            .flat_map(|pixel_color| quantize::<T>(*pixel_color))
+            .collect()
+    }
+}
+
+/// The encoders we support, chosen by the extension of the output file
+enum OutputFormat {
+    Ppm,
+    Png,
+    // Linear, unclamped floating point formats
+    OpenExr,
+    RadianceHdr,
+    Pfm,
+}
+
+impl OutputFormat {
+    fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("ppm") => OutputFormat::Ppm,
+            Some("png") => OutputFormat::Png,
+            Some("exr") => OutputFormat::OpenExr,
+            Some("hdr") => OutputFormat::RadianceHdr,
+            Some("pfm") => OutputFormat::Pfm,
+            _ => panic!(
+                "unsupported output format for {}, use one of .ppm, .png, .exr, .hdr or .pfm",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Encodes the framebuffer into `config.file`, in the format implied by its extension
+pub fn write_image(config: &OutConfig, framebuffer: &Framebuffer) -> io::Result<()> {
+    let path = Path::new(&config.file);
+    match OutputFormat::from_path(path) {
+        OutputFormat::Ppm if config.binary_ppm => write_binary_ppm(path, framebuffer),
+        OutputFormat::Ppm => write_ascii_ppm(path, framebuffer),
+        OutputFormat::Png => write_png(path, framebuffer, config.bit_depth),
+        OutputFormat::OpenExr => write_float_image(path, framebuffer, ImageFormat::OpenExr),
+        OutputFormat::RadianceHdr => write_float_image(path, framebuffer, ImageFormat::Hdr),
+        OutputFormat::Pfm => write_pfm(path, framebuffer),
+    }
+}
+
+/// Plain (P3) PPM - every channel is written as ASCII text
+fn write_ascii_ppm(path: &Path, framebuffer: &Framebuffer) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "P3\n{} {}\n255", framebuffer.width, framebuffer.height)?;
+    for pixel_color in &framebuffer.pixels {
+        write_color(&mut out, *pixel_color);
+    }
+    out.flush()
+}
+
+/// Raw (P6) PPM - every channel is written as a single byte
+fn write_binary_ppm(path: &Path, framebuffer: &Framebuffer) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "P6\n{} {}\n255", framebuffer.width, framebuffer.height)?;
+    out.write_all(&framebuffer.quantized::<u8>())?;
+    out.flush()
+}
+
+/// PNG with either 8 or 16 bits per channel
+fn write_png(path: &Path, framebuffer: &Framebuffer, bit_depth: u8) -> io::Result<()> {
+    let (width, height) = (framebuffer.width as u32, framebuffer.height as u32);
+    let image = match bit_depth {
+        8 => DynamicImage::ImageRgb8(
+            ImageBuffer::from_raw(width, height, framebuffer.quantized::<u8>())
+                .expect("framebuffer dimensions match its pixel count"),
+        ),
+        16 => DynamicImage::ImageRgb16(
+            ImageBuffer::from_raw(width, height, framebuffer.quantized::<u16>())
+                .expect("framebuffer dimensions match its pixel count"),
+        ),
+        _ => panic!("unsupported PNG bit depth {}, use 8 or 16", bit_depth),
+    };
+    image
+        .save_with_format(path, ImageFormat::Png)
+        .map_err(io::Error::other)
+}
+
+/// OpenEXR or Radiance HDR - the linear colors are written as is, without any gamma correction or
+/// clamping
+fn write_float_image(
+    path: &Path,
+    framebuffer: &Framebuffer,
+    format: ImageFormat,
+) -> io::Result<()> {
+    let image = DynamicImage::ImageRgb32F(
+        ImageBuffer::from_raw(
+            framebuffer.width as u32,
+            framebuffer.height as u32,
+            framebuffer.linear(),
+        )
+        .expect("framebuffer dimensions match its pixel count"),
+    );
+    image
+        .save_with_format(path, format)
+        .map_err(io::Error::other)
+}
+
+/// Portable float map - linear 32-bit floats, little endian (signalled by the negative scale) and
+/// stored bottom row first
+fn write_pfm(path: &Path, framebuffer: &Framebuffer) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(
+        out,
+        "PF\n{} {}\n-1.0",
+        framebuffer.width, framebuffer.height
+    )?;
+    for row in framebuffer
+        .linear()
+        .chunks_exact(3 * framebuffer.width)
+        .rev()
+    {
+        for channel in row {
+            out.write_all(&channel.to_le_bytes())?;
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,150 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::vec3::{Point3, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+/// The patterns that can be built on top of Perlin noise
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoisePattern {
+    /// Fractal (multi-octave) Perlin noise
+    Perlin,
+    /// The sum of the absolute values of each octave, giving a billowy, smoke-like look
+    Turbulence,
+    /// Sine stripes along the z axis, perturbed by turbulence
+    Marble,
+    /// Concentric rings around the y axis, perturbed by turbulence
+    Wood,
+}
+
+/// Gradient noise on a lattice of random unit vectors. The lattice is generated from a seed so
+/// that renders are reproducible, and is immutable afterwards so that it can be shared between
+/// the render threads.
+pub struct Perlin {
+    random_vectors: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let random_vectors = (0..POINT_COUNT)
+            .map(|_| loop {
+                let candidate = Vec3::new(
+                    rng.random_range(-1.0..1.0),
+                    rng.random_range(-1.0..1.0),
+                    rng.random_range(-1.0..1.0),
+                );
+                // Rejection sampling keeps the gradient directions uniformly distributed
+                if (1.0e-8..1.0).contains(&candidate.length_squared()) {
+                    break candidate.into_unit();
+                }
+            })
+            .collect();
+        let mut permutation = || {
+            let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+            perm.shuffle(&mut rng);
+            perm
+        };
+
+        Perlin {
+            perm_x: permutation(),
+            perm_y: permutation(),
+            perm_z: permutation(),
+            random_vectors,
+        }
+    }
+
+    /// Returns noise in the range [-1, 1] that varies smoothly over space
+    pub fn noise(&self, point: Point3) -> f64 {
+        let (u, v, w) = (
+            point.0 - point.0.floor(),
+            point.1 - point.1.floor(),
+            point.2 - point.2.floor(),
+        );
+        let (i, j, k) = (
+            point.0.floor() as i64,
+            point.1.floor() as i64,
+            point.2.floor() as i64,
+        );
+
+        // Hermite smoothing avoids grid artifacts
+        let (uu, vv, ww) = (
+            u * u * (3.0 - 2.0 * u),
+            v * v * (3.0 - 2.0 * v),
+            w * w * (3.0 - 2.0 * w),
+        );
+
+        let mut accumulated = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = self.random_vectors[self.perm_x[wrap(i + di)]
+                        ^ self.perm_y[wrap(j + dj)]
+                        ^ self.perm_z[wrap(k + dk)]];
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3::new(u - fi, v - fj, w - fk);
+                    accumulated += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * gradient.dot(weight);
+                }
+            }
+        }
+        accumulated
+    }
+
+    /// Sums `octaves` layers of noise, each at double the frequency and half the amplitude of the
+    /// last. Returns a value in (roughly) [-1, 1].
+    pub fn fractal(&self, point: Point3, octaves: u32) -> f64 {
+        self.octaves(point, octaves, |noise| noise)
+    }
+
+    /// Same as `fractal` but sums the absolute value of each layer, so the result is in [0, 1]
+    pub fn turbulence(&self, point: Point3, octaves: u32) -> f64 {
+        self.octaves(point, octaves, f64::abs)
+    }
+
+    fn octaves(&self, point: Point3, octaves: u32, f: impl Fn(f64) -> f64) -> f64 {
+        let mut accumulated = 0.0;
+        let mut point = point;
+        let mut weight = 1.0;
+        let mut total_weight = 0.0;
+
+        for _ in 0..octaves.max(1) {
+            accumulated += weight * f(self.noise(point));
+            total_weight += weight;
+            weight *= 0.5;
+            point = 2.0 * point;
+        }
+
+        accumulated / total_weight
+    }
+
+    /// Evaluates the pattern at the given point, returning a blend factor in [0, 1]
+    pub fn pattern(&self, pattern: NoisePattern, point: Point3, octaves: u32) -> f64 {
+        match pattern {
+            NoisePattern::Perlin => 0.5 * (1.0 + self.fractal(point, octaves)),
+            NoisePattern::Turbulence => self.turbulence(point, octaves),
+            NoisePattern::Marble => {
+                0.5 * (1.0 + (point.2 + 10.0 * self.turbulence(point, octaves)).sin())
+            }
+            NoisePattern::Wood => {
+                let rings = (point.0 * point.0 + point.2 * point.2).sqrt()
+                    + 2.0 * self.turbulence(point, octaves);
+                rings - rings.floor()
+            }
+        }
+        .clamp(0.0, 1.0)
+    }
+}
+
+/// Maps a lattice coordinate onto an index into the permutation tables
+#[inline]
+fn wrap(i: i64) -> usize {
+    i.rem_euclid(POINT_COUNT as i64) as usize
+}
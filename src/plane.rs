@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    common::math::{Interval, INFINITY},
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+/// An infinite plane through `point`. The front face is the one that `normal` points out of.
+pub struct Plane {
+    point: Point3,
+    normal: Vec3,
+    /// Unit vectors spanning the plane, used for the surface coordinates
+    tangent: Vec3,
+    bitangent: Vec3,
+    material: Arc<Material>,
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vec3, material: Arc<Material>) -> Self {
+        let normal = normal.into_unit();
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        Plane {
+            point,
+            normal,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        let denominator = self.normal.dot(ray.direction());
+        if denominator.abs() < 1.0e-8 {
+            // The ray is parallel to the plane
+            return None;
+        }
+
+        let t = self.normal.dot(self.point - ray.origin()) / denominator;
+        if !interval.surrounds(t) {
+            return None;
+        }
+
+        let mut record = HitRecord::new(ray, t, self.normal, self.material.clone());
+        // The surface coordinates are just the distances along the tangents from `point`, so
+        // (wrapping) textures repeat every unit
+        let offset = record.point - self.point;
+        (record.u, record.v) = (offset.dot(self.tangent), offset.dot(self.bitangent));
+        Some(record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let everywhere = Interval::new(-INFINITY, INFINITY);
+        Aabb::new(everywhere, everywhere, everywhere)
+    }
+}
@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use crate::{
+    color::{luminance, Color},
+    common::math::{random, PI},
+    hittable::HitRecord,
+    material::ScatterRecord,
+    microfacet::{fresnel_dielectric, Frame, Ggx},
+    ray::Ray,
+    texture::Texture,
+    vec3::Vec3,
+};
+
+/// A single "uber" material in the spirit of Disney's principled BSDF, whose parameters follow
+/// glTF's metallic-roughness model (and its clearcoat, sheen, specular, ior and transmission
+/// extensions). It layers a clear coat over a blend of a metal and a dielectric base, where the
+/// dielectric is either a glossy coat over a diffuse surface or rough glass.
+pub struct Principled {
+    /// The diffuse albedo of dielectrics, the reflectance of metals and the tint of transmitted
+    /// light
+    pub base_color: Arc<Texture>,
+    /// In [0, 1], blends from a dielectric to a metal
+    pub metallic: f64,
+    /// In [0, 1], shared by the specular reflection and transmission
+    pub roughness: f64,
+    /// In [0, 1], scales the strength of dielectric specular reflections
+    pub specular: f64,
+    /// The refractive index of the dielectric
+    pub ior: f64,
+    /// In [0, 1], the strength of the clear coat
+    pub clearcoat: f64,
+    pub clearcoat_roughness: f64,
+    /// The color of the soft, grazing-angle reflections of cloth-like surfaces
+    pub sheen_color: Color,
+    /// In [0, 1], blends the dielectric from an opaque diffuse surface to glass
+    pub transmission: f64,
+}
+
+/// The lobes that make up the material, in the order of `Shading::lobe_probabilities`
+const DIFFUSE: usize = 0;
+const SPECULAR: usize = 1;
+const TRANSMISSION: usize = 2;
+const CLEARCOAT: usize = 3;
+
+/// Clear coats are modelled as a thin layer of a typical varnish (refractive index 1.5)
+const CLEARCOAT_IOR: f64 = 1.5;
+
+/// Specular lobes can't be perfectly sharp, since they are mixed with lobes that are sampled
+/// differently
+const MIN_ROUGHNESS: f64 = 0.05;
+
+/// The material evaluated at a hit point, as seen from a particular direction
+struct Shading<'a> {
+    material: &'a Principled,
+    frame: Frame,
+    wo: Vec3,
+    base_color: Color,
+    /// The refractive index of the far side of the surface relative to the near side
+    eta: f64,
+    specular: Ggx,
+    clearcoat: Ggx,
+    /// The fraction of light that makes it through the clear coat to the layers beneath
+    coat_transmittance: f64,
+    /// The chance of sampling each lobe, in proportion to the energy it is expected to reflect
+    lobe_probabilities: [f64; 4],
+}
+
+impl Principled {
+    pub fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let shading = self.shade(ray, rec)?;
+        let wi = shading.sample()?;
+        let (value, pdf) = shading.eval(wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        // Weigh the direction by the combined density of every lobe that could have picked it,
+        // rather than just the one that did, which keeps the variance down where lobes overlap
+        Some(ScatterRecord {
+            attenuation: value / pdf,
+            scattered: Ray::new(rec.point, shading.frame.to_world(wi), ray.time()),
+            pdf,
+            is_specular: false,
+        })
+    }
+
+    /// See `Material::eval`
+    pub fn eval(&self, ray: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        self.shade(ray, rec).map_or(Color::from(0.0), |shading| {
+            shading
+                .eval(shading.frame.to_local(direction.into_unit()))
+                .0
+        })
+    }
+
+    /// See `Material::pdf`
+    pub fn pdf(&self, ray: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        self.shade(ray, rec).map_or(0.0, |shading| {
+            shading
+                .eval(shading.frame.to_local(direction.into_unit()))
+                .1
+        })
+    }
+
+    fn shade(&self, ray: &Ray, rec: &HitRecord) -> Option<Shading<'_>> {
+        let frame = Frame::new(rec.normal);
+        let wo = frame.to_local(-ray.direction().into_unit());
+        if wo.2 <= 0.0 {
+            return None;
+        }
+
+        let base_color = self.base_color.value(rec.u, rec.v, rec.point);
+        let eta = if rec.did_hit_front_frace {
+            self.ior
+        } else {
+            1.0 / self.ior
+        };
+        let coat_transmittance = 1.0 - self.clearcoat * fresnel_dielectric(wo.2, CLEARCOAT_IOR);
+
+        let dielectric = 1.0 - self.metallic;
+        let opaque = dielectric * (1.0 - self.transmission);
+        let specular_reflectance = fresnel_dielectric(wo.2, eta) * self.specular;
+        let energies = [
+            opaque
+                * (1.0 - specular_reflectance)
+                * luminance(base_color + self.sheen_color)
+                * coat_transmittance,
+            luminance(self.specular_fresnel(base_color, wo.2, eta)) * coat_transmittance,
+            dielectric * self.transmission * coat_transmittance,
+            self.clearcoat * fresnel_dielectric(wo.2, CLEARCOAT_IOR),
+        ];
+        let total: f64 = energies.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        Some(Shading {
+            material: self,
+            frame,
+            wo,
+            base_color,
+            eta,
+            specular: Ggx::new(self.roughness.max(MIN_ROUGHNESS)),
+            clearcoat: Ggx::new(self.clearcoat_roughness.max(MIN_ROUGHNESS)),
+            coat_transmittance,
+            lobe_probabilities: energies.map(|energy| energy / total),
+        })
+    }
+
+    /// The reflectance of the specular lobe, blending between the (white) Fresnel reflectance of
+    /// the opaque dielectric and that of the metal (Schlick's approximation tinted by the base
+    /// color)
+    fn specular_fresnel(&self, base_color: Color, cos_theta: f64, eta: f64) -> Color {
+        let dielectric = (1.0 - self.metallic) * (1.0 - self.transmission);
+        let metal = base_color + (Color::from(1.0) - base_color) * (1.0 - cos_theta).powi(5);
+        dielectric * self.specular * fresnel_dielectric(cos_theta, eta) + self.metallic * metal
+    }
+}
+
+impl Shading<'_> {
+    /// Picks a lobe and samples a direction (in shading space) from it
+    fn sample(&self) -> Option<Vec3> {
+        let mut choice = random();
+        let lobe = self
+            .lobe_probabilities
+            .iter()
+            .position(|&probability| {
+                choice -= probability;
+                choice < 0.0
+            })
+            .unwrap_or(CLEARCOAT);
+
+        match lobe {
+            DIFFUSE => {
+                // Cosine-weighted, like `Material::Lambertian`
+                let wi = Vec3::new(0.0, 0.0, 1.0) + Vec3::on_unit_sphere();
+                Some(if wi.is_near_zero() {
+                    Vec3::new(0.0, 0.0, 1.0)
+                } else {
+                    wi.into_unit()
+                })
+            }
+            SPECULAR => Some((-self.wo).reflect(self.specular.sample_visible_normal(self.wo))),
+            TRANSMISSION => self
+                .specular
+                .sample_dielectric(self.wo, self.eta)
+                .map(|(wi, _)| wi),
+            _ => Some((-self.wo).reflect(self.clearcoat.sample_visible_normal(self.wo))),
+        }
+    }
+
+    /// Returns the BSDF times the cosine for scattering light arriving along `wi` (in shading
+    /// space) back along `wo`, and the density with which `sample` picks `wi`
+    fn eval(&self, wi: Vec3) -> (Color, f64) {
+        let material = self.material;
+        let wo = self.wo;
+        let probabilities = self.lobe_probabilities;
+        let mut value = Color::from(0.0);
+        let mut pdf = 0.0;
+
+        let half = wo + wi;
+        let m = half.into_unit();
+        if !half.is_near_zero() && wo.dot(m) > 0.0 {
+            // The glossy lobes may sample directions below the surface, so their densities count
+            // everywhere, even where they don't reflect any light
+            pdf += probabilities[SPECULAR] * self.specular.visible_normal_pdf(wo, m)
+                / (4.0 * wo.dot(m));
+            pdf += probabilities[CLEARCOAT] * self.clearcoat.visible_normal_pdf(wo, m)
+                / (4.0 * wo.dot(m));
+
+            if wi.2 > 0.0 {
+                let specular = self.specular.d(m) * self.specular.g(wo, wi) / (4.0 * wo.2);
+                value = value
+                    + material.specular_fresnel(self.base_color, wo.dot(m), self.eta)
+                        * (specular * self.coat_transmittance);
+
+                let clearcoat = self.clearcoat.d(m) * self.clearcoat.g(wo, wi) / (4.0 * wo.2);
+                value = value
+                    + Color::from(
+                        material.clearcoat
+                            * fresnel_dielectric(wo.dot(m), CLEARCOAT_IOR)
+                            * clearcoat,
+                    );
+
+                // Light that isn't reflected by the specular coat is scattered diffusely, with
+                // a sheen that rises towards grazing angles
+                let opaque = (1.0 - material.metallic) * (1.0 - material.transmission);
+                let specular_reflectance = fresnel_dielectric(wo.2, self.eta) * material.specular;
+                let diffuse = self.base_color * ((1.0 - specular_reflectance) / PI)
+                    + material.sheen_color * (1.0 - wi.dot(m)).max(0.0).powi(5);
+                value = value + diffuse * (opaque * self.coat_transmittance * wi.2);
+            }
+        }
+        if wi.2 > 0.0 {
+            pdf += probabilities[DIFFUSE] * wi.2 / PI;
+        }
+
+        if let Some((transmission, transmission_pdf)) =
+            self.specular.eval_dielectric(wo, wi, self.eta)
+        {
+            // Only the refracted light is tinted, as if by colored glass
+            let tint = if wi.2 > 0.0 {
+                Color::from(1.0)
+            } else {
+                self.base_color
+            };
+            let weight =
+                (1.0 - material.metallic) * material.transmission * self.coat_transmittance;
+            value = value + tint * (transmission * weight);
+            pdf += probabilities[TRANSMISSION] * transmission_pdf;
+        }
+
+        (value, pdf)
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    common::math::{random, Interval, INFINITY},
+    hittable::{solid_angle_pdf, HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+/// A parallelogram with one corner at `corner` and its two adjacent edges given by `u` and `v`.
+/// The front face is the one that `u ✕ v` points out of.
+pub struct Quad {
+    corner: Point3,
+    u: Vec3,
+    v: Vec3,
+    /// Used to compute the planar coordinates of the hit point relative to the edges
+    w: Vec3,
+    normal: Vec3,
+    /// The plane containing the quad is the set of points p satisfying `normal · p = d`
+    d: f64,
+    area: f64,
+    material: Arc<Material>,
+    bbox: Aabb,
+}
+
+impl Quad {
+    pub fn new(corner: Point3, u: Vec3, v: Vec3, material: Arc<Material>) -> Self {
+        let n = u.cross(&v);
+        let normal = n.into_unit();
+        let bbox = Aabb::surrounding(
+            &Aabb::from_points(corner, corner + u + v),
+            &Aabb::from_points(corner + u, corner + v),
+        );
+        Quad {
+            corner,
+            u,
+            v,
+            w: n / n.length_squared(),
+            normal,
+            d: normal.dot(corner),
+            area: n.length(),
+            material,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        let denominator = self.normal.dot(ray.direction());
+        if denominator.abs() < 1.0e-8 {
+            // The ray is parallel to the plane
+            return None;
+        }
+
+        let t = (self.d - self.normal.dot(ray.origin())) / denominator;
+        if !interval.surrounds(t) {
+            return None;
+        }
+
+        // Express the hit point in terms of the edges, it lies within the quad iff both
+        // coordinates are in [0, 1]
+        let planar_hit_vector = ray.at(t) - self.corner;
+        let alpha = self.w.dot(planar_hit_vector.cross(&self.v));
+        let beta = self.w.dot(self.u.cross(&planar_hit_vector));
+        let unit_interval = Interval::new(0.0, 1.0);
+        if !unit_interval.contains(alpha) || !unit_interval.contains(beta) {
+            return None;
+        }
+
+        let mut record = HitRecord::new(ray, t, self.normal, self.material.clone());
+        (record.u, record.v) = (alpha, beta);
+        Some(record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        self.hit(ray, Interval::new(0.001, INFINITY))
+            .map_or(0.0, |rec| solid_angle_pdf(ray, &rec, self.area))
+    }
+
+    fn random(&self, origin: Point3, _time: f64) -> Vec3 {
+        self.corner + random() * self.u + random() * self.v - origin
+    }
+}
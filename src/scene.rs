@@ -0,0 +1,405 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    color::Color,
+    common::math::deg_to_rad,
+    config::{
+        DispersionKind, MaterialConfig, MaterialKind, ObjectConfig, RefractiveIndexConfig,
+        ShapeConfig, TextureConfig, TextureKind, ThinFilmConfig, TransformConfig,
+    },
+    constant_medium::ConstantMedium,
+    cuboid::Cuboid,
+    disc::Disc,
+    grid_volume::GridVolume,
+    hittable::Hittable,
+    material::{Material, RefractiveIndex},
+    mesh::Mesh,
+    perlin::Perlin,
+    plane::Plane,
+    principled::Principled,
+    quad::Quad,
+    spectrum::{MAX_WAVELENGTH, MIN_WAVELENGTH},
+    sphere::Sphere,
+    texture::Texture,
+    thin_film::ThinFilm,
+    transform::Transform,
+    triangle::Triangle,
+    vec3::{Mat4, Point3, Vec3},
+    world::World,
+};
+
+impl From<&MaterialKind> for Material {
+    fn from(kind: &MaterialKind) -> Self {
+        match kind {
+            MaterialKind::Lambertian { albedo } => Material::Lambertian {
+                albedo: Arc::new(Texture::from(albedo)),
+            },
+            MaterialKind::Metal { albedo, fuzz } => Material::Metal {
+                albedo: Arc::new(Texture::from(albedo)),
+                fuzz: *fuzz,
+            },
+            MaterialKind::Conductor {
+                preset,
+                eta,
+                k,
+                roughness,
+                film,
+            } => {
+                let (eta, k) = match (preset, eta, k) {
+                    (_, Some(eta), Some(k)) => (Color::from(eta.clone()), Color::from(k.clone())),
+                    (Some(preset), None, None) => preset.refractive_index(),
+                    _ => panic!("conductors need either a preset or both eta and k"),
+                };
+                Material::Conductor {
+                    eta,
+                    k,
+                    roughness: *roughness,
+                    film: film.as_ref().map(ThinFilm::from),
+                }
+            }
+            MaterialKind::Dielectric {
+                refractive_index,
+                absorption,
+                film,
+            } => Material::Dielectric {
+                refractive_index: RefractiveIndex::from(refractive_index),
+                absorption: Color::from(absorption.clone()),
+                film: film.as_ref().map(ThinFilm::from),
+            },
+            MaterialKind::RoughDielectric {
+                refractive_index,
+                roughness,
+                absorption,
+            } => Material::RoughDielectric {
+                refractive_index: *refractive_index,
+                roughness: *roughness,
+                absorption: Color::from(absorption.clone()),
+            },
+            MaterialKind::Subsurface {
+                refractive_index,
+                roughness,
+                scattering,
+                absorption,
+                albedo,
+                mean_free_path,
+            } => {
+                let (scattering, absorption) =
+                    match (scattering, absorption, albedo, mean_free_path) {
+                        (Some(scattering), Some(absorption), None, None) => {
+                            (Color::from(scattering.clone()), Color::from(absorption.clone()))
+                        }
+                        (None, None, Some(albedo), Some(mean_free_path)) => {
+                            if mean_free_path
+                                .iter()
+                                .any(|distance| distance.is_nan() || *distance <= 0.0)
+                            {
+                                panic!("subsurface materials need a positive mean_free_path");
+                            }
+                            if albedo.iter().any(|a| !(0.0..=1.0).contains(a)) {
+                                panic!("subsurface materials need an albedo in [0, 1]");
+                            }
+                            let albedo = Color::from(albedo.clone());
+                            let extinction =
+                                Color::from(1.0) / Color::from(mean_free_path.clone());
+                            (albedo * extinction, (Color::from(1.0) - albedo) * extinction)
+                        }
+                        _ => panic!(
+                            "subsurface materials need either scattering and absorption, or albedo and mean_free_path"
+                        ),
+                    };
+                // Light inside must travel a finite, non-zero distance on average before
+                // interacting, or the random walk samples infinite or NaN distances
+                let channels = |color: Color| [color.0, color.1, color.2];
+                let valid = channels(scattering)
+                    .into_iter()
+                    .chain(channels(absorption))
+                    .all(|sigma| sigma.is_finite() && sigma >= 0.0)
+                    && channels(scattering + absorption)
+                        .into_iter()
+                        .all(|sigma| sigma > 0.0);
+                if !valid {
+                    panic!(
+                        "subsurface materials need non-negative scattering and absorption, adding up to more than zero"
+                    );
+                }
+                Material::Subsurface {
+                    refractive_index: *refractive_index,
+                    roughness: *roughness,
+                    scattering,
+                    absorption,
+                }
+            }
+            MaterialKind::Principled {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+                ior,
+                clearcoat,
+                clearcoat_roughness,
+                sheen_color,
+                transmission,
+            } => Material::Principled(Principled {
+                base_color: Arc::new(Texture::from(base_color)),
+                metallic: *metallic,
+                roughness: *roughness,
+                specular: *specular,
+                ior: *ior,
+                clearcoat: *clearcoat,
+                clearcoat_roughness: *clearcoat_roughness,
+                sheen_color: Color::from(sheen_color.clone()),
+                transmission: *transmission,
+            }),
+            MaterialKind::Isotropic { albedo } => Material::Isotropic {
+                albedo: Arc::new(Texture::from(albedo)),
+            },
+            MaterialKind::DiffuseLight { emit } => Material::DiffuseLight {
+                emit: Color::from(emit.clone()),
+            },
+        }
+    }
+}
+
+impl From<&RefractiveIndexConfig> for RefractiveIndex {
+    fn from(config: &RefractiveIndexConfig) -> Self {
+        let coefficients = |values: &Vec<f64>| -> [f64; 3] {
+            values
+                .as_slice()
+                .try_into()
+                .expect("sellmeier equations need exactly three b and c coefficients")
+        };
+        match config {
+            RefractiveIndexConfig::Constant(n) => RefractiveIndex::Constant(*n),
+            RefractiveIndexConfig::Dispersive(DispersionKind::Cauchy { a, b }) => {
+                RefractiveIndex::Cauchy { a: *a, b: *b }
+            }
+            RefractiveIndexConfig::Dispersive(DispersionKind::Sellmeier { b, c }) => {
+                // Each c is the square of a wavelength (in micrometers) at which the index blows
+                // up, which must not be one that paths are traced at
+                let visible = (MIN_WAVELENGTH / 1000.0).powi(2)..=(MAX_WAVELENGTH / 1000.0).powi(2);
+                if c.iter().any(|c| visible.contains(c)) {
+                    panic!(
+                        "sellmeier c coefficients must lie outside the visible range of squared wavelengths (0.1444 to 0.6084 µm²)"
+                    );
+                }
+                RefractiveIndex::Sellmeier {
+                    b: coefficients(b),
+                    c: coefficients(c),
+                }
+            }
+        }
+    }
+}
+
+impl From<&ThinFilmConfig> for ThinFilm {
+    fn from(config: &ThinFilmConfig) -> Self {
+        ThinFilm {
+            ior: config.ior,
+            thickness_min: config.thickness_min,
+            thickness_max: config.thickness_max,
+            thickness: config
+                .thickness
+                .as_ref()
+                .map(|thickness| Arc::new(Texture::from(thickness))),
+        }
+    }
+}
+
+impl From<&TextureConfig> for Texture {
+    fn from(config: &TextureConfig) -> Self {
+        match config {
+            TextureConfig::Solid(color) => Texture::Solid(Color::from(color.clone())),
+            TextureConfig::Texture(TextureKind::Checker { scale, even, odd }) => Texture::Checker {
+                scale: *scale,
+                even: Arc::new(Texture::from(even.as_ref())),
+                odd: Arc::new(Texture::from(odd.as_ref())),
+            },
+            TextureConfig::Texture(TextureKind::Image { file }) => Texture::from_image(file)
+                .unwrap_or_else(|err| panic!("loading texture {}: {}", file, err)),
+            TextureConfig::Texture(TextureKind::Noise {
+                pattern,
+                frequency,
+                octaves,
+                seed,
+                low,
+                high,
+            }) => Texture::Noise {
+                perlin: Perlin::new(*seed),
+                pattern: *pattern,
+                frequency: *frequency,
+                octaves: *octaves,
+                low: Color::from(low.clone()),
+                high: Color::from(high.clone()),
+            },
+        }
+    }
+}
+
+/// Builds the world described by the `[[materials]]` and `[[objects]]` tables of the config.
+/// Panics if an object references a material that isn't defined, or if its geometry is malformed.
+pub fn build_world(materials: &[MaterialConfig], objects: &[ObjectConfig]) -> World {
+    let mut named_materials: HashMap<&str, Arc<Material>> = HashMap::new();
+    for material in materials {
+        if named_materials
+            .insert(&material.name, Arc::new(Material::from(&material.kind)))
+            .is_some()
+        {
+            panic!("material \"{}\" is defined more than once", material.name);
+        }
+    }
+    let lookup = |name: &str| -> Arc<Material> {
+        named_materials
+            .get(name)
+            .unwrap_or_else(|| panic!("object references unknown material \"{}\"", name))
+            .clone()
+    };
+
+    // Meshes are only loaded once per file and material, so that instancing them is cheap
+    let mut meshes: HashMap<(&str, &str), Arc<dyn Hittable>> = HashMap::new();
+
+    let mut world = World::new();
+    for object in objects {
+        let shape: Arc<dyn Hittable> = match &object.shape {
+            ShapeConfig::Sphere {
+                center,
+                center_end,
+                radius,
+                material,
+            } => {
+                let center = Point3::from(center.clone());
+                let center_end = center_end.clone().map_or(center, Point3::from);
+                Arc::new(Sphere::moving(
+                    center,
+                    center_end,
+                    *radius,
+                    lookup(material),
+                ))
+            }
+            ShapeConfig::Triangle {
+                vertices,
+                normals,
+                texcoords,
+                material,
+            } => {
+                let mut triangle =
+                    Triangle::new(to_triple(vertices).map(Point3::from), lookup(material));
+                if let Some(normals) = normals {
+                    triangle = triangle.with_normals(to_triple(normals).map(Vec3::from));
+                }
+                if let Some(texcoords) = texcoords {
+                    triangle =
+                        triangle.with_texcoords(to_triple(texcoords).map(|uv| (uv[0], uv[1])));
+                }
+                Arc::new(triangle)
+            }
+            ShapeConfig::Quad {
+                corner,
+                u,
+                v,
+                material,
+            } => Arc::new(Quad::new(
+                Point3::from(corner.clone()),
+                Vec3::from(u.clone()),
+                Vec3::from(v.clone()),
+                lookup(material),
+            )),
+            ShapeConfig::Plane {
+                point,
+                normal,
+                material,
+            } => Arc::new(Plane::new(
+                Point3::from(point.clone()),
+                Vec3::from(normal.clone()),
+                lookup(material),
+            )),
+            ShapeConfig::Disc {
+                center,
+                normal,
+                radius,
+                material,
+            } => Arc::new(Disc::new(
+                Point3::from(center.clone()),
+                Vec3::from(normal.clone()),
+                *radius,
+                lookup(material),
+            )),
+            ShapeConfig::Cuboid { min, max, material } => Arc::new(Cuboid::new(
+                Point3::from(min.clone()),
+                Point3::from(max.clone()),
+                lookup(material),
+            )),
+            ShapeConfig::Volume {
+                file,
+                min,
+                max,
+                density_scale,
+                material,
+            } => Arc::new(
+                GridVolume::from_raw(
+                    file,
+                    Point3::from(min.clone()),
+                    Point3::from(max.clone()),
+                    *density_scale,
+                    lookup(material),
+                )
+                .unwrap_or_else(|err| panic!("loading volume {}: {}", file, err)),
+            ),
+            ShapeConfig::Mesh { file, material } => meshes
+                .entry((file, material))
+                .or_insert_with(|| {
+                    Arc::new(
+                        Mesh::from_obj(file, lookup(material))
+                            .unwrap_or_else(|err| panic!("loading mesh {}: {}", file, err)),
+                    )
+                })
+                .clone(),
+        };
+
+        let matrix = to_matrix(&object.transform);
+        let placed: Arc<dyn Hittable> = if !object.keyframes.is_empty() {
+            let keyframes = object
+                .keyframes
+                .iter()
+                .map(|keyframe| (keyframe.time, to_matrix(&keyframe.transform) * matrix))
+                .collect();
+            Arc::new(Transform::keyframed(shape, keyframes))
+        } else if !object.transform.is_empty() {
+            Arc::new(Transform::new(shape, matrix))
+        } else {
+            shape
+        };
+
+        match object.density {
+            // The (placed) shape becomes the boundary of the medium, scattering light according
+            // to its material
+            Some(density) => world.add(Box::new(ConstantMedium::new(
+                placed,
+                density,
+                lookup(object.shape.material()),
+            ))),
+            None => world.add_shared(placed),
+        }
+    }
+
+    world
+}
+
+/// Composes the transforms into a single matrix, with the first transform applied first
+fn to_matrix(transforms: &[TransformConfig]) -> Mat4 {
+    transforms.iter().fold(Mat4::IDENTITY, |matrix, transform| {
+        let next = match transform {
+            TransformConfig::Translate(offset) => Mat4::translation(Vec3::from(offset.clone())),
+            TransformConfig::Rotate { axis, angle } => {
+                Mat4::rotation(Vec3::from(axis.clone()), deg_to_rad(*angle))
+            }
+            TransformConfig::Scale(factors) => Mat4::scaling(Vec3::from(factors.clone())),
+        };
+        next * matrix
+    })
+}
+
+/// Converts the per-vertex attributes of a triangle into an array
+fn to_triple(values: &[Vec<f64>]) -> [Vec<f64>; 3] {
+    <[Vec<f64>; 3]>::try_from(values.to_vec())
+        .unwrap_or_else(|_| panic!("triangles need exactly 3 vertices, got {}", values.len()))
+}
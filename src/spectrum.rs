@@ -0,0 +1,172 @@
+use std::sync::OnceLock;
+
+use crate::{
+    color::Color,
+    common::math::{lerp, random_in_range},
+};
+
+/// The range of visible wavelengths (in nanometers) that paths are traced at in spectral mode
+pub const MIN_WAVELENGTH: f64 = 380.0;
+pub const MAX_WAVELENGTH: f64 = 780.0;
+
+/// The wavelengths (in nanometers) that a camera path is traced at in spectral mode. There are
+/// three, spread evenly over the visible range, so that the spectral radiance carried along the
+/// path fits in a `Color` and all the usual color math applies unchanged.
+#[derive(Copy, Clone, Debug)]
+pub struct Wavelengths {
+    lambdas: [f64; 3],
+    /// Set once the path has passed through a dispersive material and only the first ("hero")
+    /// wavelength is still being traced
+    collapsed: bool,
+}
+
+impl Wavelengths {
+    /// Samples a random hero wavelength, with the other two rotated a third of the visible range
+    /// away from it
+    pub fn sample() -> Self {
+        let range = MAX_WAVELENGTH - MIN_WAVELENGTH;
+        let hero = random_in_range(MIN_WAVELENGTH, MAX_WAVELENGTH);
+        let lambdas = [0.0, 1.0, 2.0]
+            .map(|i| MIN_WAVELENGTH + (hero - MIN_WAVELENGTH + i * range / 3.0) % range);
+        Wavelengths {
+            lambdas,
+            collapsed: false,
+        }
+    }
+
+    /// The wavelength that dispersive materials refract the path at
+    pub fn hero(&self) -> f64 {
+        self.lambdas[0]
+    }
+
+    /// Drops every wavelength but the hero one, since the path can no longer follow all of them
+    /// once they have been dispersed in different directions. Returns the factor to scale the
+    /// path's throughput by, which keeps the estimate unbiased.
+    pub fn collapse(&mut self) -> Color {
+        if self.collapsed {
+            return Color::from(1.0);
+        }
+        self.collapsed = true;
+        Color::new(3.0, 0.0, 0.0)
+    }
+
+    /// Evaluates a smooth spectrum matching the given RGB color at each of the wavelengths
+    pub fn upsample(&self, rgb: Color) -> Color {
+        let [a, b, c] = self.lambdas.map(|lambda| rgb_to_spectrum(rgb, lambda));
+        Color::new(a, b, c)
+    }
+
+    /// Converts the spectral radiance carried at each wavelength into a linear RGB color
+    pub fn to_rgb(self, radiance: Color) -> Color {
+        // Each wavelength is a sample of the integral over the visible range against the color
+        // matching functions
+        let weight = (MAX_WAVELENGTH - MIN_WAVELENGTH) / 3.0;
+        let xyz = [radiance.0, radiance.1, radiance.2]
+            .iter()
+            .zip(self.lambdas)
+            .fold(Color::from(0.0), |xyz, (&value, lambda)| {
+                xyz + cie_xyz(lambda) * (value * weight)
+            });
+        xyz_to_rgb(xyz) / white_point()
+    }
+}
+
+/// The CIE 1931 standard observer's color matching functions, using the multi-lobe Gaussian fit
+/// from Wyman, Sloan & Shirley, "Simple Analytic Approximations to the CIE XYZ Color Matching
+/// Functions" (2013)
+fn cie_xyz(lambda: f64) -> Color {
+    let gaussian = |mu: f64, sigma_low: f64, sigma_high: f64| {
+        let sigma = if lambda < mu { sigma_low } else { sigma_high };
+        (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+    };
+    Color::new(
+        1.056 * gaussian(599.8, 37.9, 31.0) + 0.362 * gaussian(442.0, 16.0, 26.7)
+            - 0.065 * gaussian(501.1, 20.4, 26.2),
+        0.821 * gaussian(568.8, 46.9, 40.5) + 0.286 * gaussian(530.9, 16.3, 31.1),
+        1.217 * gaussian(437.0, 11.8, 36.0) + 0.681 * gaussian(459.0, 26.0, 13.8),
+    )
+}
+
+/// Converts CIE XYZ into linear sRGB (Rec. 709 primaries)
+fn xyz_to_rgb(xyz: Color) -> Color {
+    Color::new(
+        3.2404542 * xyz.0 - 1.5371385 * xyz.1 - 0.4985314 * xyz.2,
+        -0.9692660 * xyz.0 + 1.8760108 * xyz.1 + 0.0415560 * xyz.2,
+        0.0556434 * xyz.0 - 0.2040259 * xyz.1 + 1.0572252 * xyz.2,
+    )
+}
+
+/// The RGB color of the flat, equal-energy spectrum (which upsampled white turns into).
+/// Dividing by it keeps white objects under white lights white.
+fn white_point() -> Color {
+    static WHITE_POINT: OnceLock<Color> = OnceLock::new();
+    *WHITE_POINT.get_or_init(|| {
+        let xyz = (MIN_WAVELENGTH as usize..MAX_WAVELENGTH as usize)
+            .fold(Color::from(0.0), |xyz, lambda| {
+                xyz + cie_xyz(lambda as f64 + 0.5)
+            });
+        xyz_to_rgb(xyz)
+    })
+}
+
+/// Smits' basis spectra (from "An RGB to Spectrum Conversion for Reflectances", 1999), sampled
+/// in ten equal bins spanning 380-720nm
+const SMITS_WHITE: [f64; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f64; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f64; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f64; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f64; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f64; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f64; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+/// Smits' method - the color is built up from as much white as possible, then as much of a
+/// secondary color (cyan, magenta or yellow) and finally a primary (red, green or blue)
+fn rgb_to_spectrum(rgb: Color, lambda: f64) -> f64 {
+    let basis = |spectrum: &[f64; 10]| smits_basis(spectrum, lambda);
+    let (r, g, b) = (rgb.0, rgb.1, rgb.2);
+    if r <= g && r <= b {
+        let rest = if g <= b {
+            (g - r) * basis(&SMITS_CYAN) + (b - g) * basis(&SMITS_BLUE)
+        } else {
+            (b - r) * basis(&SMITS_CYAN) + (g - b) * basis(&SMITS_GREEN)
+        };
+        r * basis(&SMITS_WHITE) + rest
+    } else if g <= r && g <= b {
+        let rest = if r <= b {
+            (r - g) * basis(&SMITS_MAGENTA) + (b - r) * basis(&SMITS_BLUE)
+        } else {
+            (b - g) * basis(&SMITS_MAGENTA) + (r - b) * basis(&SMITS_RED)
+        };
+        g * basis(&SMITS_WHITE) + rest
+    } else {
+        let rest = if r <= g {
+            (r - b) * basis(&SMITS_YELLOW) + (g - r) * basis(&SMITS_GREEN)
+        } else {
+            (g - b) * basis(&SMITS_YELLOW) + (r - g) * basis(&SMITS_RED)
+        };
+        b * basis(&SMITS_WHITE) + rest
+    }
+}
+
+/// Linearly interpolates between the bin centers of one of Smits' spectra
+fn smits_basis(spectrum: &[f64; 10], lambda: f64) -> f64 {
+    const FIRST_CENTER: f64 = 380.0 + 17.0;
+    const BIN_WIDTH: f64 = 34.0;
+    let position = ((lambda - FIRST_CENTER) / BIN_WIDTH).clamp(0.0, 9.0);
+    let bin = (position as usize).min(8);
+    lerp(spectrum[bin], spectrum[bin + 1], position - bin as f64)
+}
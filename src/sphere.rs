@@ -1,33 +1,69 @@
 use std::sync::Arc;
 
 use crate::{
-    common::math::Interval,
+    aabb::Aabb,
+    common::math::{random, Interval, INFINITY, PI},
     hittable::{HitRecord, Hittable},
     material::Material,
     ray::Ray,
-    vec3::Point3,
+    vec3::{Point3, Vec3},
 };
 
 pub struct Sphere {
+    /// The center at time 0
     center: Point3,
+    /// How far the center moves per unit of time
+    velocity: Vec3,
     radius: f64,
     material: Arc<Material>,
+    bbox: Aabb,
 }
 
 impl Sphere {
     pub fn new(center: Point3, radius: f64, material: Arc<Material>) -> Self {
+        Sphere::moving(center, center, radius, material)
+    }
+
+    /// A sphere whose center moves linearly from `center_start` at time 0 to `center_end` at
+    /// time 1
+    pub fn moving(
+        center_start: Point3,
+        center_end: Point3,
+        radius: f64,
+        material: Arc<Material>,
+    ) -> Self {
+        let radius_vec = Vec3::from(radius);
+        let bbox = Aabb::surrounding(
+            &Aabb::from_points(center_start - radius_vec, center_start + radius_vec),
+            &Aabb::from_points(center_end - radius_vec, center_end + radius_vec),
+        );
         Sphere {
-            center,
+            center: center_start,
+            velocity: center_end - center_start,
             radius,
             material,
+            bbox,
         }
     }
+
+    fn center_at(&self, time: f64) -> Point3 {
+        self.center + time * self.velocity
+    }
+
+    /// Maps a point on the unit sphere onto spherical surface coordinates - u is the angle around
+    /// the Y axis (starting from -X) and v is the angle from -Y up to +Y, both normalized to [0, 1]
+    fn get_uv(point: Point3) -> (f64, f64) {
+        let theta = (-point.1).clamp(-1.0, 1.0).acos();
+        let phi = (-point.2).atan2(point.0) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
         // Ray-Sphere intersection
-        let oc = self.center - ray.origin();
+        let center = self.center_at(ray.time());
+        let oc = center - ray.origin();
         let a = ray.direction().length_squared();
         let h = ray.direction().dot(oc);
         let c = oc.length_squared() - self.radius * self.radius;
@@ -48,17 +84,53 @@ impl Hittable for Sphere {
             }
         }
 
-        // TODO: use a builder instead
-        let mut record = HitRecord {
-            t: root,
-            point: ray.at(root),
-            material: self.material.clone(),
-            normal: Default::default(),
-            did_hit_front_frace: Default::default(),
-        };
-        let outward_normal = (record.point - self.center) / self.radius; // Normalize
-        record.set_face_normal(ray, outward_normal);
+        let outward_normal = (ray.at(root) - center) / self.radius; // Normalize
+        let mut record = HitRecord::new(ray, root, outward_normal, self.material.clone());
+        (record.u, record.v) = Sphere::get_uv(outward_normal);
 
         Some(record)
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        if self.hit(ray, Interval::new(0.001, INFINITY)).is_none() {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center_at(ray.time()) - ray.origin()).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            // From the inside every direction hits the sphere
+            return 1.0 / (4.0 * PI);
+        }
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: Point3, time: f64) -> Vec3 {
+        let to_center = self.center_at(time) - origin;
+        let distance_squared = to_center.length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return Vec3::on_unit_sphere();
+        }
+
+        // Sample uniformly within the cone of directions from the origin that hit the sphere,
+        // which (unlike sampling its area) never wastes samples on the far side
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let cos_theta = 1.0 + random() * (cos_theta_max - 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random();
+
+        let w = to_center.into_unit();
+        let (u, v) = w.orthonormal_basis();
+        sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * w
+    }
 }
@@ -0,0 +1,101 @@
+use std::{path::Path, sync::Arc};
+
+use image::Rgb32FImage;
+
+use crate::{
+    color::{gamma_to_linear, Color},
+    common::math::lerp,
+    perlin::{NoisePattern, Perlin},
+    vec3::Point3,
+};
+
+/// Represents the spatially varying color of a surface, looked up by the surface coordinates
+/// (u, v) and/or the position of the hit point
+pub enum Texture {
+    /// The same color everywhere
+    Solid(Color),
+
+    /// Alternates between two textures in a 3D checkerboard of cubes with sides of length `scale`.
+    /// Since the pattern is solid (i.e defined throughout space) it doesn't depend on the surface
+    /// coordinates at all.
+    Checker {
+        scale: f64,
+        even: Arc<Texture>,
+        odd: Arc<Texture>,
+    },
+
+    /// An image wrapped onto the surface using its (u, v) coordinates, with (0, 0) being the
+    /// bottom left of the image. Colors are stored in linear space.
+    Image(Rgb32FImage),
+
+    /// A procedural (solid) pattern built from Perlin noise, blending between `low` and `high`
+    Noise {
+        perlin: Perlin,
+        pattern: NoisePattern,
+        /// Scales the hit point before evaluating the noise - higher is busier
+        frequency: f64,
+        /// The number of layers of noise summed to produce fractal detail
+        octaves: u32,
+        low: Color,
+        high: Color,
+    },
+}
+
+impl Texture {
+    /// Loads a PNG/JPEG image texture. The images are assumed to be gamma encoded, so they are
+    /// converted back into linear space.
+    pub fn from_image(path: impl AsRef<Path>) -> image::ImageResult<Self> {
+        let mut image = image::open(path)?.into_rgb32f();
+        image
+            .pixels_mut()
+            .flat_map(|pixel| pixel.0.iter_mut())
+            .for_each(|channel| *channel = gamma_to_linear(*channel as f64) as f32);
+        Ok(Texture::Image(image))
+    }
+
+    pub fn value(&self, u: f64, v: f64, point: Point3) -> Color {
+        match self {
+            Texture::Solid(color) => *color,
+            Texture::Checker { scale, even, odd } => {
+                let cell = point.map(|x| (x / scale).floor());
+                if (cell.0 + cell.1 + cell.2).rem_euclid(2.0) == 0.0 {
+                    even.value(u, v, point)
+                } else {
+                    odd.value(u, v, point)
+                }
+            }
+            Texture::Image(image) => {
+                if image.width() == 0 || image.height() == 0 {
+                    // Debugging aid for missing image data
+                    return Color::new(0.0, 1.0, 1.0);
+                }
+                // Coordinates outside [0, 1] wrap around so that textures can tile. Note the
+                // flipped v since image rows start at the top.
+                let u = u.rem_euclid(1.0);
+                let v = 1.0 - v.rem_euclid(1.0);
+
+                let x = ((u * image.width() as f64) as u32).min(image.width() - 1);
+                let y = ((v * image.height() as f64) as u32).min(image.height() - 1);
+                let pixel = image.get_pixel(x, y);
+                Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64)
+            }
+            Texture::Noise {
+                perlin,
+                pattern,
+                frequency,
+                octaves,
+                low,
+                high,
+            } => {
+                let t = perlin.pattern(*pattern, *frequency * point, *octaves);
+                lerp(*low, *high, t)
+            }
+        }
+    }
+}
+
+impl From<Color> for Texture {
+    fn from(color: Color) -> Self {
+        Texture::Solid(color)
+    }
+}
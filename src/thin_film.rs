@@ -0,0 +1,329 @@
+use std::{
+    ops::{Add, Div, Mul, Sub},
+    sync::Arc,
+};
+
+use crate::{
+    color::Color,
+    common::math::{lerp, PI},
+    hittable::HitRecord,
+    texture::Texture,
+};
+
+/// A transparent coating (e.g of soap or oil) only a few hundred nanometers thick. Light reflected
+/// off its top interferes with light reflected off its bottom, and since whether the two add up
+/// or cancel out depends on the wavelength, the coating shimmers in rainbow colors that shift
+/// with the viewing angle and thickness. Parameters follow glTF's iridescence extension.
+pub struct ThinFilm {
+    /// The refractive index of the film
+    pub ior: f64,
+    /// The range of thicknesses (in nanometers) that `thickness` maps onto
+    pub thickness_min: f64,
+    pub thickness_max: f64,
+    /// The green channel (in [0, 1]) picks the thickness within the range. The film is
+    /// `thickness_max` thick everywhere without a texture.
+    pub thickness: Option<Arc<Texture>>,
+}
+
+/// The wavelengths (in nanometers) that stand in for the red, green and blue channels when not
+/// rendering in spectral mode
+const RGB_WAVELENGTHS: [f64; 3] = [630.0, 532.0, 465.0];
+
+impl ThinFilm {
+    /// The fraction of light reflected off a film coating a dielectric with refractive index
+    /// `refractive_index`, for light arriving at an angle with cosine `cos_theta` to the normal.
+    /// Evaluated at the ray's wavelength if it has one, otherwise for each color channel.
+    pub fn reflectance_dielectric(
+        &self,
+        rec: &HitRecord,
+        cos_theta: f64,
+        refractive_index: f64,
+        wavelength: Option<f64>,
+    ) -> Color {
+        // The film is on the outside of the object, so rays from within pass through the object
+        // before the film and leave into air (assumed to surround the object)
+        let (outside, substrate) = if rec.did_hit_front_frace {
+            (1.0, refractive_index)
+        } else {
+            (refractive_index, 1.0)
+        };
+        self.reflectance(rec, cos_theta, outside, wavelength, |_| {
+            Complex::new(substrate, 0.0)
+        })
+    }
+
+    /// Like `reflectance_dielectric`, for a film coating a conductor with the complex refractive
+    /// index `eta + ik` (per color channel)
+    pub fn reflectance_conductor(
+        &self,
+        rec: &HitRecord,
+        cos_theta: f64,
+        eta: Color,
+        k: Color,
+        wavelength: Option<f64>,
+    ) -> Color {
+        self.reflectance(rec, cos_theta, 1.0, wavelength, |lambda| {
+            Complex::new(channel_at(eta, lambda), channel_at(k, lambda))
+        })
+    }
+
+    fn reflectance(
+        &self,
+        rec: &HitRecord,
+        cos_theta: f64,
+        outside: f64,
+        wavelength: Option<f64>,
+        substrate: impl Fn(f64) -> Complex,
+    ) -> Color {
+        let thickness = self
+            .thickness
+            .as_ref()
+            .map_or(self.thickness_max, |texture| {
+                let t = texture.value(rec.u, rec.v, rec.point).1.clamp(0.0, 1.0);
+                lerp(self.thickness_min, self.thickness_max, t)
+            });
+        // Clamped only to guard against rounding error
+        let reflectance = |lambda: f64| {
+            airy_reflectance(
+                cos_theta,
+                outside,
+                self.ior,
+                substrate(lambda),
+                thickness,
+                lambda,
+            )
+            .clamp(0.0, 1.0)
+        };
+        match wavelength {
+            Some(lambda) => Color::from(reflectance(lambda)),
+            None => {
+                let [r, g, b] = RGB_WAVELENGTHS.map(reflectance);
+                Color::new(r, g, b)
+            }
+        }
+    }
+}
+
+/// The reflectance of a film with refractive index `film` and the given thickness, lying between
+/// a medium with refractive index `outside` (that the light arrives from) and a substrate with the
+/// complex refractive index `substrate`. Sums the light bouncing back and forth inside the film
+/// over every number of round trips (Airy's formula), averaged over both polarizations.
+fn airy_reflectance(
+    cos_theta: f64,
+    outside: f64,
+    film: f64,
+    substrate: Complex,
+    thickness: f64,
+    wavelength: f64,
+) -> f64 {
+    let cos_1 = cos_theta.clamp(0.0, 1.0);
+    let sin_squared_2 = (outside / film).powi(2) * (1.0 - cos_1 * cos_1);
+    if sin_squared_2 >= 1.0 {
+        // Total internal reflection before the light even enters the film
+        return 1.0;
+    }
+    let cos_2 = (1.0 - sin_squared_2).sqrt();
+    // Complex for conductors, and when the light is totally reflected at the bottom of the film
+    let cos_3 = (Complex::from(1.0)
+        - Complex::from(film * film * sin_squared_2) / (substrate * substrate))
+        .sqrt();
+
+    // The phase picked up by a round trip through the film
+    let phase = Complex::from_angle(4.0 * PI * film * thickness * cos_2 / wavelength);
+    let reflectance = |r_12: f64, r_23: Complex| {
+        let delayed = r_23 * phase;
+        ((Complex::from(r_12) + delayed) / (Complex::from(1.0) + delayed * r_12)).norm_squared()
+    };
+
+    let (film, outside) = (Complex::from(film), Complex::from(outside));
+    let (cos_1, cos_2) = (Complex::from(cos_1), Complex::from(cos_2));
+    let r_s = reflectance(
+        fresnel_amplitude(outside, cos_1, film, cos_2).re,
+        fresnel_amplitude(film, cos_2, substrate, cos_3),
+    );
+    let r_p = reflectance(
+        fresnel_amplitude(outside, cos_2, film, cos_1).re,
+        fresnel_amplitude(film, cos_3, substrate, cos_2),
+    );
+    0.5 * (r_s + r_p)
+}
+
+/// The Fresnel amplitude coefficient for the interface between media `a` and `b`. The
+/// s-polarized coefficient for light traveling at angles with cosines `cos_a` and `cos_b` to the
+/// normal in each medium, or the p-polarized one with the cosines swapped.
+fn fresnel_amplitude(a: Complex, cos_a: Complex, b: Complex, cos_b: Complex) -> Complex {
+    (a * cos_a - b * cos_b) / (a * cos_a + b * cos_b)
+}
+
+/// Interpolates a per-channel quantity between the wavelengths that the channels stand in for
+fn channel_at(color: Color, wavelength: f64) -> f64 {
+    let [red, green, blue] = RGB_WAVELENGTHS;
+    if wavelength >= green {
+        lerp(
+            color.1,
+            color.0,
+            ((wavelength - green) / (red - green)).min(1.0),
+        )
+    } else {
+        lerp(
+            color.1,
+            color.2,
+            ((green - wavelength) / (green - blue)).min(1.0),
+        )
+    }
+}
+
+/// Just enough complex arithmetic for the Fresnel equations of absorbing media
+#[derive(Copy, Clone, Debug)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    /// The unit complex number at the given angle (in radians), i.e `e^(i * angle)`
+    fn from_angle(angle: f64) -> Self {
+        Complex::new(angle.cos(), angle.sin())
+    }
+
+    fn norm_squared(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    /// The principal square root
+    fn sqrt(self) -> Self {
+        let norm = self.norm_squared().sqrt();
+        let re = (0.5 * (norm + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (norm - self.re)).max(0.0).sqrt();
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Complex::new(re, 0.0)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, rhs: Complex) -> Complex {
+        Complex::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, rhs: Complex) -> Complex {
+        Complex::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, rhs: Complex) -> Complex {
+        Complex::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl Mul<f64> for Complex {
+    type Output = Complex;
+
+    fn mul(self, rhs: f64) -> Complex {
+        Complex::new(self.re * rhs, self.im * rhs)
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, rhs: Complex) -> Complex {
+        let denominator = rhs.norm_squared();
+        Complex::new(
+            (self.re * rhs.re + self.im * rhs.im) / denominator,
+            (self.im * rhs.re - self.re * rhs.im) / denominator,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::microfacet::{fresnel_conductor, fresnel_dielectric};
+
+    const COSINES: [f64; 6] = [1.0, 0.9, 0.7, 0.5, 0.2, 0.01];
+    /// Gold at roughly the wavelength of green light
+    const GOLD: (f64, f64) = (0.43, 2.45);
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn vanishing_film_leaves_dielectric_fresnel() {
+        for cos_theta in COSINES {
+            let expected = fresnel_dielectric(cos_theta, 1.5);
+            let without_thickness =
+                airy_reflectance(cos_theta, 1.0, 1.33, Complex::from(1.5), 0.0, 550.0);
+            let matching_outside =
+                airy_reflectance(cos_theta, 1.0, 1.0, Complex::from(1.5), 300.0, 550.0);
+            assert_close(without_thickness, expected);
+            assert_close(matching_outside, expected);
+        }
+    }
+
+    #[test]
+    fn vanishing_film_leaves_conductor_fresnel() {
+        let (eta, k) = GOLD;
+        for cos_theta in COSINES {
+            let expected = fresnel_conductor(cos_theta, eta, k);
+            let without_thickness =
+                airy_reflectance(cos_theta, 1.0, 1.33, Complex::new(eta, k), 0.0, 550.0);
+            let matching_outside =
+                airy_reflectance(cos_theta, 1.0, 1.0, Complex::new(eta, k), 300.0, 550.0);
+            assert_close(without_thickness, expected);
+            assert_close(matching_outside, expected);
+        }
+    }
+
+    #[test]
+    fn reflectance_stays_within_unit_range() {
+        let substrates = [
+            (1.0, Complex::from(1.5)),
+            // From inside glass, where light can be totally reflected at the bottom of the film
+            (1.5, Complex::from(1.0)),
+            (1.0, Complex::new(GOLD.0, GOLD.1)),
+        ];
+        for (outside, substrate) in substrates {
+            for film in [1.0, 1.33, 2.0] {
+                for thickness in [0.0, 50.0, 250.0, 1000.0] {
+                    for wavelength in [400.0, 550.0, 700.0] {
+                        for step in 0..=100 {
+                            let cos_theta = step as f64 / 100.0;
+                            let reflectance = airy_reflectance(
+                                cos_theta, outside, film, substrate, thickness, wavelength,
+                            );
+                            assert!(
+                                (-1e-9..=1.0 + 1e-9).contains(&reflectance),
+                                "reflectance {reflectance} out of range"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    common::math::{lerp, Interval, INFINITY},
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    vec3::{Mat4, Point3, Quat, Vec3},
+};
+
+/// Places an instance of some object in the world through an affine transformation (any
+/// combination of translations, rotations and scales), which may change over time. The object
+/// itself is shared, so it can be instanced many times without duplicating its geometry.
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    motion: Motion,
+    bbox: Aabb,
+}
+
+enum Motion {
+    Static {
+        /// Object space -> world space
+        matrix: Mat4,
+        placement: Box<Placement>,
+    },
+    /// Sorted by time. Rays cast before the first / after the last keyframe see the object as
+    /// placed by that keyframe.
+    Keyframed(Vec<Keyframe>),
+}
+
+/// The matrices needed to intersect rays with a placed object. Only the inverse transformation
+/// is involved, so keyframed placements can be built for every ray without inverting anything.
+struct Placement {
+    /// World space -> object space
+    inverse: Mat4,
+    /// Normals transform by the inverse transpose, which preserves which side of the surface the
+    /// ray is on
+    normal_matrix: Mat4,
+}
+
+/// The transformation at some instant, decomposed so that it can be smoothly interpolated. The
+/// parts are kept inverted, since intersecting rays only needs the inverse transformation.
+struct Keyframe {
+    time: f64,
+    translation: Vec3,
+    rotation: Quat,
+    inverse_scale: Mat4,
+}
+
+impl Placement {
+    fn new(inverse: Mat4) -> Self {
+        Placement {
+            inverse,
+            normal_matrix: inverse.transpose(),
+        }
+    }
+}
+
+/// Returns the inverse transformation (world space -> object space) at the given time
+fn interpolate_inverse(keyframes: &[Keyframe], time: f64) -> Mat4 {
+    let next = keyframes.partition_point(|keyframe| keyframe.time <= time);
+    if next == 0 {
+        return keyframes[0].inverse();
+    } else if next == keyframes.len() {
+        return keyframes[next - 1].inverse();
+    }
+
+    let (a, b) = (&keyframes[next - 1], &keyframes[next]);
+    let t = (time - a.time) / (b.time - a.time);
+    a.inverse_scale.lerp(&b.inverse_scale, t)
+        * a.rotation.slerp(&b.rotation, t).to_matrix().transpose()
+        * Mat4::translation(-lerp(a.translation, b.translation, t))
+}
+
+impl Keyframe {
+    fn inverse(&self) -> Mat4 {
+        self.inverse_scale
+            * self.rotation.to_matrix().transpose()
+            * Mat4::translation(-self.translation)
+    }
+}
+
+impl Transform {
+    /// Panics if `matrix` isn't invertible
+    pub fn new(object: Arc<dyn Hittable>, matrix: Mat4) -> Self {
+        let bbox = Transform::transform_bbox(&object.bounding_box(), &matrix);
+        let inverse = matrix
+            .inverse()
+            .expect("instance transforms must be invertible");
+        Transform {
+            object,
+            motion: Motion::Static {
+                matrix,
+                placement: Box::new(Placement::new(inverse)),
+            },
+            bbox,
+        }
+    }
+
+    /// An instance that moves through the given (time, transformation) keyframes. The translation,
+    /// rotation and scale are interpolated separately so that rotating objects stay rigid in
+    /// between keyframes. Panics if there are no keyframes, any matrix isn't invertible, or only
+    /// some of them mirror the object.
+    pub fn keyframed(object: Arc<dyn Hittable>, mut keyframes: Vec<(f64, Mat4)>) -> Self {
+        if keyframes.is_empty() {
+            panic!("keyframed transforms need at least one keyframe");
+        }
+        keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // Turning an object inside out can't happen gradually - the scale would have to pass
+        // through zero on the way. Keyframes of the same handedness always interpolate through
+        // invertible transformations.
+        let mirrored = |matrix: &Mat4| matrix.determinant() < 0.0;
+        if keyframes
+            .windows(2)
+            .any(|pair| mirrored(&pair[0].1) != mirrored(&pair[1].1))
+        {
+            panic!("keyframed transforms need either all or none of their keyframes to mirror");
+        }
+
+        let keyframes: Vec<Keyframe> = keyframes
+            .into_iter()
+            .map(|(time, matrix)| {
+                let invertible = "instance transforms must be invertible";
+                let (translation, rotation, scale) = matrix.decompose().expect(invertible);
+                Keyframe {
+                    time,
+                    translation,
+                    rotation,
+                    inverse_scale: scale.inverse().expect(invertible),
+                }
+            })
+            .collect();
+
+        let bbox = Transform::keyframed_bbox(&object.bounding_box(), &keyframes);
+
+        Transform {
+            object,
+            motion: Motion::Keyframed(keyframes),
+            bbox,
+        }
+    }
+
+    /// Bounds the object over its whole path by sampling each segment between keyframes. A
+    /// rotating box bulges out between samples, so each pair of consecutive samples is padded by
+    /// the farthest any corner moves between them. Corners sweep along arcs of at most a few
+    /// degrees, which never stray further from their endpoints than the chord between them.
+    fn keyframed_bbox(object_bbox: &Aabb, keyframes: &[Keyframe]) -> Aabb {
+        const STEPS_PER_SEGMENT: usize = 64;
+        if !object_bbox.is_bounded() {
+            return Transform::everywhere();
+        }
+
+        // Keyframes only interpolate through singular transformations by rounding error, and
+        // then there's no telling where the object goes
+        let corners_at = |time: f64| {
+            let matrix = interpolate_inverse(keyframes, time).inverse()?;
+            Some(Transform::corners(object_bbox).map(|corner| matrix.transform_point(corner)))
+        };
+        let Some(mut previous) = corners_at(-INFINITY) else {
+            return Transform::everywhere();
+        };
+        let mut bbox = Transform::enclosing(&previous);
+        for segment in keyframes.windows(2) {
+            for step in 1..=STEPS_PER_SEGMENT {
+                let time = lerp(
+                    segment[0].time,
+                    segment[1].time,
+                    step as f64 / STEPS_PER_SEGMENT as f64,
+                );
+                let Some(corners) = corners_at(time) else {
+                    return Transform::everywhere();
+                };
+                let movement = previous
+                    .iter()
+                    .zip(&corners)
+                    .map(|(&a, &b)| (b - a).length())
+                    .fold(0.0, f64::max);
+                let swept = Aabb::surrounding(
+                    &Transform::enclosing(&previous),
+                    &Transform::enclosing(&corners),
+                );
+                bbox = Aabb::surrounding(&bbox, &swept.expand(movement));
+                previous = corners;
+            }
+        }
+        bbox
+    }
+
+    /// Returns the world space box enclosing the transformed corners of the object space box
+    fn transform_bbox(bbox: &Aabb, matrix: &Mat4) -> Aabb {
+        if !bbox.is_bounded() {
+            return Transform::everywhere();
+        }
+        Transform::enclosing(&Transform::corners(bbox).map(|corner| matrix.transform_point(corner)))
+    }
+
+    /// Unbounded objects stay unbounded wherever they are placed
+    fn everywhere() -> Aabb {
+        let everywhere = Interval::new(-INFINITY, INFINITY);
+        Aabb::new(everywhere, everywhere, everywhere)
+    }
+
+    /// Returns the tightest box enclosing all the given points
+    fn enclosing(points: &[Point3]) -> Aabb {
+        points.iter().fold(Aabb::EMPTY, |bbox, &point| {
+            Aabb::surrounding(&bbox, &Aabb::from_points(point, point))
+        })
+    }
+
+    /// The eight corners of a box
+    fn corners(bbox: &Aabb) -> [Point3; 8] {
+        let (x, y, z) = (
+            bbox.axis_interval(0),
+            bbox.axis_interval(1),
+            bbox.axis_interval(2),
+        );
+        std::array::from_fn(|i| {
+            Point3::new(
+                if i & 1 == 0 { x.min() } else { x.max() },
+                if i & 2 == 0 { y.min() } else { y.max() },
+                if i & 4 == 0 { z.min() } else { z.max() },
+            )
+        })
+    }
+
+    /// Calls `f` with the placement of the object at the given time
+    fn with_placement<R>(&self, time: f64, f: impl FnOnce(&Placement) -> R) -> R {
+        match &self.motion {
+            Motion::Static { placement, .. } => f(placement),
+            Motion::Keyframed(keyframes) => {
+                f(&Placement::new(interpolate_inverse(keyframes, time)))
+            }
+        }
+    }
+
+    fn hit_placed(
+        &self,
+        placement: &Placement,
+        ray: &Ray,
+        interval: Interval,
+    ) -> Option<HitRecord> {
+        // Move the ray into object space. The direction isn't normalized, so the ray parameter t
+        // is the same in both spaces.
+        let object_ray = Ray::new(
+            placement.inverse.transform_point(ray.origin()),
+            placement.inverse.transform_vector(ray.direction()),
+            ray.time(),
+        );
+
+        let mut record = self.object.hit(&object_ray, interval)?;
+
+        // ...and the hit back into world space, where it lies at the same t along the ray
+        record.point = ray.at(record.t);
+        record.normal = placement
+            .normal_matrix
+            .transform_vector(record.normal)
+            .into_unit();
+
+        Some(record)
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        self.with_placement(ray.time(), |placement| {
+            self.hit_placed(placement, ray, interval)
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+
+    /// The object's own density over object space directions, converted to world space ones.
+    /// Rotations, translations and uniform scales preserve solid angles, but non-uniform scales
+    /// squash them unevenly: a world space direction ω maps to the object space direction along
+    /// L⁻¹ω (where L is the linear part of the transformation), and the density picks up that
+    /// map's Jacobian |det L⁻¹| / |L⁻¹ω|³ for unit ω.
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        self.with_placement(ray.time(), |placement| {
+            let direction = placement
+                .inverse
+                .transform_vector(ray.direction().into_unit());
+            let object_pdf = self.object.pdf_value(&Ray::new(
+                placement.inverse.transform_point(ray.origin()),
+                direction,
+                ray.time(),
+            ));
+            object_pdf * placement.inverse.determinant().abs() / direction.length().powi(3)
+        })
+    }
+
+    fn random(&self, origin: Point3, time: f64) -> Vec3 {
+        let (matrix, inverse) = match &self.motion {
+            Motion::Static { matrix, placement } => (*matrix, placement.inverse),
+            Motion::Keyframed(keyframes) => {
+                let inverse = interpolate_inverse(keyframes, time);
+                // Only singular by rounding error (see `keyframed_bbox`), when any direction will
+                // do
+                let Some(matrix) = inverse.inverse() else {
+                    return Vec3::on_unit_sphere();
+                };
+                (matrix, inverse)
+            }
+        };
+        let object_origin = inverse.transform_point(origin);
+        matrix.transform_vector(self.object.random(object_origin, time))
+    }
+}
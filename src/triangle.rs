@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    common::math::{random, Interval, INFINITY},
+    hittable::{solid_angle_pdf, HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+pub struct Triangle {
+    vertices: [Point3; 3],
+    /// Per-vertex normals used to smoothly shade meshes that approximate curved surfaces. When
+    /// absent the (flat) geometric normal is used instead.
+    normals: Option<[Vec3; 3]>,
+    /// Per-vertex texture coordinates. When absent the barycentric coordinates are used as the
+    /// surface coordinates instead.
+    texcoords: Option<[(f64, f64); 3]>,
+    material: Arc<Material>,
+    bbox: Aabb,
+}
+
+impl Triangle {
+    /// Vertices are expected in counter-clockwise order when looking at the front face
+    pub fn new(vertices: [Point3; 3], material: Arc<Material>) -> Self {
+        let [v0, v1, v2] = vertices;
+        let bbox = Aabb::surrounding(&Aabb::from_points(v0, v1), &Aabb::from_points(v2, v2));
+        Triangle {
+            vertices,
+            normals: None,
+            texcoords: None,
+            material,
+            bbox,
+        }
+    }
+
+    /// Interpolates the shading normal at the hit point from the given per-vertex normals
+    pub fn with_normals(self, normals: [Vec3; 3]) -> Self {
+        Triangle {
+            normals: Some(normals.map(Vec3::into_unit)),
+            ..self
+        }
+    }
+
+    /// Interpolates the surface coordinates at the hit point from the given per-vertex texture
+    /// coordinates
+    pub fn with_texcoords(self, texcoords: [(f64, f64); 3]) -> Self {
+        Triangle {
+            texcoords: Some(texcoords),
+            ..self
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        // Möller–Trumbore ray-triangle intersection
+        const EPSILON: f64 = 1.0e-12;
+        let [v0, v1, v2] = self.vertices;
+        let edge_1 = v1 - v0;
+        let edge_2 = v2 - v0;
+
+        let p = ray.direction().cross(&edge_2);
+        let determinant = edge_1.dot(p);
+        if determinant.abs() < EPSILON {
+            // The ray is parallel to the plane of the triangle
+            return None;
+        }
+        let inverse_determinant = 1.0 / determinant;
+
+        let unit_interval = Interval::new(0.0, 1.0);
+        let s = ray.origin() - v0;
+        let b1 = s.dot(p) * inverse_determinant;
+        if !unit_interval.contains(b1) {
+            return None;
+        }
+
+        let q = s.cross(&edge_1);
+        let b2 = ray.direction().dot(q) * inverse_determinant;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = edge_2.dot(q) * inverse_determinant;
+        if !interval.surrounds(t) {
+            return None;
+        }
+
+        let geometric_normal = edge_1.cross(&edge_2).into_unit();
+        let mut record = HitRecord::new(ray, t, geometric_normal, self.material.clone());
+        record.barycentric = Some((b1, b2));
+        (record.u, record.v) = match self.texcoords {
+            Some([uv0, uv1, uv2]) => (
+                (1.0 - b1 - b2) * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+                (1.0 - b1 - b2) * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+            ),
+            None => (b1, b2),
+        };
+
+        if let Some([n0, n1, n2]) = self.normals {
+            // Keep the interpolated normal on the same side as the geometric one so that the
+            // front-face convention still holds
+            let shading_normal = ((1.0 - b1 - b2) * n0 + b1 * n1 + b2 * n2).into_unit();
+            record.normal = if record.did_hit_front_frace {
+                shading_normal
+            } else {
+                -shading_normal
+            };
+        }
+
+        Some(record)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        let [v0, v1, v2] = self.vertices;
+        let area = 0.5 * (v1 - v0).cross(&(v2 - v0)).length();
+        self.hit(ray, Interval::new(0.001, INFINITY))
+            .map_or(0.0, |rec| solid_angle_pdf(ray, &rec, area))
+    }
+
+    fn random(&self, origin: Point3, _time: f64) -> Vec3 {
+        // Uniformly distributed barycentric coordinates - the square root keeps samples from
+        // bunching up towards v0
+        let [v0, v1, v2] = self.vertices;
+        let s = random().sqrt();
+        let t = random();
+        let (b1, b2) = (s * (1.0 - t), s * t);
+        v0 + b1 * (v1 - v0) + b2 * (v2 - v0) - origin
+    }
+}
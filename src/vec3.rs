@@ -1,4 +1,4 @@
-use crate::common::math::{random, random_in_range};
+use crate::common::math::{lerp, random, random_in_range};
 
 /// Represents a three-dimensional vector of floats used to represent colors, coordinates, etc
 #[derive(Copy, Clone, Default, Debug)]
@@ -117,6 +117,20 @@ impl Vec3 {
         self.0.abs() < EPSILON && self.1.abs() < EPSILON && self.2.abs() < EPSILON
     }
 
+    /// Returns two unit vectors that together with self (assumed to be of unit length) form an
+    /// orthonormal basis
+    pub fn orthonormal_basis(&self) -> (Self, Self) {
+        // Building an Orthonormal Basis, Revisited (Duff et al.) - branchless and free of
+        // singularities
+        let sign = 1.0_f64.copysign(self.2);
+        let a = -1.0 / (sign + self.2);
+        let b = self.0 * self.1 * a;
+        (
+            Vec3(1.0 + sign * self.0 * self.0 * a, sign * b, -sign * self.0),
+            Vec3(b, sign + self.1 * self.1 * a, -self.1),
+        )
+    }
+
     /// Reflects self about the provided normal
     pub fn reflect(&self, normal: Vec3) -> Self {
         *self - 2.0 * self.dot(normal) * normal
@@ -217,3 +231,326 @@ impl std::ops::Neg for Vec3 {
         self.map(std::ops::Neg::neg)
     }
 }
+
+/// A 4x4 matrix (stored row by row) representing an affine transformation, i.e the last row is
+/// always (0, 0, 0, 1)
+#[derive(Copy, Clone, Debug)]
+pub struct Mat4(pub [[f64; 4]; 4]);
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    pub fn translation(offset: Vec3) -> Self {
+        Mat4([
+            [1.0, 0.0, 0.0, offset.0],
+            [0.0, 1.0, 0.0, offset.1],
+            [0.0, 0.0, 1.0, offset.2],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scaling(factors: Vec3) -> Self {
+        Mat4([
+            [factors.0, 0.0, 0.0, 0.0],
+            [0.0, factors.1, 0.0, 0.0],
+            [0.0, 0.0, factors.2, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Counter-clockwise rotation (when looking down the axis towards the origin) by `angle`
+    /// radians about `axis`
+    pub fn rotation(axis: Vec3, angle: f64) -> Self {
+        let Vec3(x, y, z) = axis.into_unit();
+        let (sin, cos) = angle.sin_cos();
+        let k = 1.0 - cos;
+        Mat4([
+            [
+                cos + x * x * k,
+                x * y * k - z * sin,
+                x * z * k + y * sin,
+                0.0,
+            ],
+            [
+                y * x * k + z * sin,
+                cos + y * y * k,
+                y * z * k - x * sin,
+                0.0,
+            ],
+            [
+                z * x * k - y * sin,
+                z * y * k + x * sin,
+                cos + z * z * k,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Applies the full transformation (including translation) to a point
+    pub fn transform_point(&self, point: Point3) -> Point3 {
+        self.transform_vector(point) + Vec3(self.0[0][3], self.0[1][3], self.0[2][3])
+    }
+
+    /// Applies the linear part of the transformation (i.e no translation) to a direction
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.0;
+        Vec3(
+            m[0][0] * v.0 + m[0][1] * v.1 + m[0][2] * v.2,
+            m[1][0] * v.0 + m[1][1] * v.1 + m[1][2] * v.2,
+            m[2][0] * v.0 + m[2][1] * v.1 + m[2][2] * v.2,
+        )
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut transposed = [[0.0; 4]; 4];
+        for (i, row) in self.0.iter().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                transposed[j][i] = *value;
+            }
+        }
+        Mat4(transposed)
+    }
+
+    /// The determinant of the linear part, which is negative for transformations that mirror
+    pub fn determinant(&self) -> f64 {
+        let m = &self.0;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Inverts the affine transformation. Returns None if it is singular (e.g scales by zero).
+    pub fn inverse(&self) -> Option<Self> {
+        let m = &self.0;
+        // Invert the linear 3x3 part through its adjugate
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+            m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+        };
+        let adjugate = [
+            [
+                cofactor(1, 2, 1, 2),
+                -cofactor(0, 2, 1, 2),
+                cofactor(0, 1, 1, 2),
+            ],
+            [
+                -cofactor(1, 2, 0, 2),
+                cofactor(0, 2, 0, 2),
+                -cofactor(0, 1, 0, 2),
+            ],
+            [
+                cofactor(1, 2, 0, 1),
+                -cofactor(0, 2, 0, 1),
+                cofactor(0, 1, 0, 1),
+            ],
+        ];
+        let determinant =
+            m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+        if determinant.abs() < 1.0e-12 {
+            return None;
+        }
+
+        let mut inverse = Mat4::IDENTITY;
+        for (inverse_row, adjugate_row) in inverse.0.iter_mut().zip(adjugate) {
+            for (value, cofactor) in inverse_row.iter_mut().zip(adjugate_row) {
+                *value = cofactor / determinant;
+            }
+        }
+        // The inverse translation undoes the original one after the inverse linear part
+        let translation = -inverse.transform_vector(Vec3(m[0][3], m[1][3], m[2][3]));
+        (inverse.0[0][3], inverse.0[1][3], inverse.0[2][3]) =
+            (translation.0, translation.1, translation.2);
+
+        Some(inverse)
+    }
+}
+
+impl Mat4 {
+    /// Splits the transformation into a translation, a rotation and the remaining scale (which
+    /// may include shear), such that self = translation * rotation * scale. Uses the polar
+    /// decomposition, which finds the rotation by repeatedly averaging the matrix with its inverse
+    /// transpose. Any mirroring is left in the scale, since rotations can't express it. Returns
+    /// None if the transformation is singular.
+    pub fn decompose(&self) -> Option<(Vec3, Quat, Mat4)> {
+        let translation = Vec3(self.0[0][3], self.0[1][3], self.0[2][3]);
+        let mut linear = *self;
+        (linear.0[0][3], linear.0[1][3], linear.0[2][3]) = (0.0, 0.0, 0.0);
+
+        let mut rotation = linear;
+        for _ in 0..100 {
+            let inverse_transpose = rotation.inverse()?.transpose();
+            let mut next = rotation;
+            let mut change: f64 = 0.0;
+            for i in 0..3 {
+                for j in 0..3 {
+                    next.0[i][j] = 0.5 * (rotation.0[i][j] + inverse_transpose.0[i][j]);
+                    change = change.max((next.0[i][j] - rotation.0[i][j]).abs());
+                }
+            }
+            rotation = next;
+            if change < 1.0e-10 {
+                break;
+            }
+        }
+
+        // The polar factor of a mirroring transformation is a rotation combined with a reflection,
+        // which negating it turns into a proper rotation (handing the reflection to the scale)
+        if rotation.determinant() < 0.0 {
+            for row in rotation.0.iter_mut().take(3) {
+                for value in row.iter_mut().take(3) {
+                    *value = -*value;
+                }
+            }
+        }
+
+        let scale = rotation.inverse()? * linear;
+        Some((translation, Quat::from_rotation_matrix(&rotation), scale))
+    }
+
+    /// Linearly interpolates each entry of the matrix
+    pub fn lerp(&self, other: &Mat4, t: f64) -> Self {
+        let mut interpolated = *self;
+        for (row, other_row) in interpolated.0.iter_mut().zip(other.0) {
+            for (value, other_value) in row.iter_mut().zip(other_row) {
+                *value = lerp(*value, other_value, t);
+            }
+        }
+        interpolated
+    }
+}
+
+/// A unit quaternion representing a rotation - these interpolate smoothly (unlike the entries
+/// of rotation matrices)
+#[derive(Copy, Clone, Debug)]
+pub struct Quat {
+    pub v: Vec3,
+    pub w: f64,
+}
+
+impl Quat {
+    /// Assumes the linear part of `m` is a pure rotation
+    pub fn from_rotation_matrix(m: &Mat4) -> Self {
+        let m = &m.0;
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        let quat = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt();
+            let w = s / 2.0;
+            let s = 0.5 / s;
+            Quat {
+                v: Vec3(
+                    (m[2][1] - m[1][2]) * s,
+                    (m[0][2] - m[2][0]) * s,
+                    (m[1][0] - m[0][1]) * s,
+                ),
+                w,
+            }
+        } else {
+            // Work off the largest diagonal entry for numerical stability
+            let i = if m[1][1] > m[0][0] { 1 } else { 0 };
+            let i = if m[2][2] > m[i][i] { 2 } else { i };
+            let (j, k) = ((i + 1) % 3, (i + 2) % 3);
+            let s = (m[i][i] - (m[j][j] + m[k][k]) + 1.0).sqrt();
+            let mut q = [0.0; 3];
+            q[i] = s * 0.5;
+            let s = if s != 0.0 { 0.5 / s } else { s };
+            q[j] = (m[j][i] + m[i][j]) * s;
+            q[k] = (m[k][i] + m[i][k]) * s;
+            Quat {
+                v: Vec3(q[0], q[1], q[2]),
+                w: (m[k][j] - m[j][k]) * s,
+            }
+        };
+        quat.normalized()
+    }
+
+    pub fn dot(&self, other: &Quat) -> f64 {
+        self.v.dot(other.v) + self.w * other.w
+    }
+
+    fn normalized(self) -> Self {
+        let length = self.dot(&self).sqrt();
+        Quat {
+            v: self.v / length,
+            w: self.w / length,
+        }
+    }
+
+    /// Spherical linear interpolation - rotates at a constant rate along the shortest arc
+    pub fn slerp(&self, other: &Quat, t: f64) -> Self {
+        let mut other = *other;
+        let mut cos_theta = self.dot(&other);
+        if cos_theta < 0.0 {
+            // q and -q are the same rotation, take the shorter way around
+            other = Quat {
+                v: -other.v,
+                w: -other.w,
+            };
+            cos_theta = -cos_theta;
+        }
+
+        if cos_theta > 0.9995 {
+            // Nearly parallel, so avoid dividing by ~0 and just lerp
+            return Quat {
+                v: lerp(self.v, other.v, t),
+                w: lerp(self.w, other.w, t),
+            }
+            .normalized();
+        }
+
+        let theta = cos_theta.clamp(-1.0, 1.0).acos();
+        let (a, b) = (
+            ((1.0 - t) * theta).sin() / theta.sin(),
+            (t * theta).sin() / theta.sin(),
+        );
+        Quat {
+            v: a * self.v + b * other.v,
+            w: a * self.w + b * other.w,
+        }
+    }
+
+    pub fn to_matrix(self) -> Mat4 {
+        let Vec3(x, y, z) = self.v;
+        let w = self.w;
+        Mat4([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+/// Matrix composition - `a * b` applies `b` first, then `a`
+impl std::ops::Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Self::Output {
+        let mut product = [[0.0; 4]; 4];
+        for (i, row) in product.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum();
+            }
+        }
+        Mat4(product)
+    }
+}
@@ -1,13 +1,25 @@
+use std::sync::Arc;
+
 use crate::{
-    common::math::Interval,
+    aabb::Aabb,
+    bvh::BvhNode,
+    common::math::{random, Interval},
     hittable::{HitRecord, Hittable},
     ray::Ray,
+    vec3::{Point3, Vec3},
 };
 
-/// Models our little raytracing world - which is just a list of Hittable objects
+/// Models our little raytracing world - which is just a list of Hittable objects. Call
+/// `build_bvh` once the scene is populated to accelerate ray intersections.
 #[derive(Default)]
 pub struct World {
-    objects: Vec<Box<dyn Hittable>>,
+    objects: Vec<Arc<dyn Hittable>>,
+    bvh: Option<BvhNode>,
+    /// Objects of infinite extent (e.g planes) are kept out of the BVH, since their boxes would
+    /// make every node along their path to the root useless. These are always tested.
+    unbounded: Vec<Arc<dyn Hittable>>,
+    /// The emissive objects, which are sampled directly to light the rest of the scene
+    lights: Vec<Arc<dyn Hittable>>,
 }
 
 impl World {
@@ -16,22 +28,87 @@ impl World {
     }
 
     pub fn add(&mut self, object: Box<dyn Hittable>) {
+        self.add_shared(Arc::from(object));
+    }
+
+    /// Same as `add`, for objects that are also referenced elsewhere (e.g instanced meshes)
+    pub fn add_shared(&mut self, object: Arc<dyn Hittable>) {
+        if object.is_emissive() {
+            self.lights.push(object.clone());
+        }
         self.objects.push(object);
+        // Any previously built hierarchy no longer covers every object
+        self.bvh = None;
+    }
+
+    /// Builds a bounding volume hierarchy over the objects added so far. Until this is called (or
+    /// after any subsequent `add`) ray intersections fall back to a linear scan of every object.
+    pub fn build_bvh(&mut self) {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = self
+            .objects
+            .iter()
+            .cloned()
+            .partition(|object| object.bounding_box().is_bounded());
+        self.bvh = (!bounded.is_empty()).then(|| BvhNode::new(bounded));
+        self.unbounded = unbounded;
+    }
+}
+
+/// Returns the closest hit among the given objects
+fn hit_closest(objects: &[Arc<dyn Hittable>], ray: &Ray, interval: Interval) -> Option<HitRecord> {
+    let mut temp_record = None;
+    let mut closest_so_far = interval.max();
+
+    for object in objects {
+        if let Some(rec) = object.hit(ray, Interval::new(interval.min(), closest_so_far)) {
+            closest_so_far = rec.t;
+            temp_record = Some(rec);
+        }
     }
+
+    temp_record
 }
 
 impl Hittable for World {
     fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
-        let mut temp_record = None;
-        let mut closest_so_far = interval.max();
-
-        for object in &self.objects {
-            if let Some(rec) = object.hit(ray, Interval::new(interval.min(), closest_so_far)) {
-                closest_so_far = rec.t;
-                temp_record = Some(rec);
+        match &self.bvh {
+            Some(bvh) => {
+                let unbounded_record = hit_closest(&self.unbounded, ray, interval);
+                let closest_so_far = unbounded_record
+                    .as_ref()
+                    .map_or(interval.max(), |rec| rec.t);
+                bvh.hit(ray, Interval::new(interval.min(), closest_so_far))
+                    .or(unbounded_record)
             }
+            None => hit_closest(&self.objects, ray, interval),
         }
+    }
 
-        temp_record
+    fn bounding_box(&self) -> Aabb {
+        self.objects.iter().fold(Aabb::EMPTY, |bbox, object| {
+            Aabb::surrounding(&bbox, &object.bounding_box())
+        })
+    }
+
+    fn is_emissive(&self) -> bool {
+        !self.lights.is_empty()
+    }
+
+    /// Each light is equally likely to be sampled, so the density is the average of theirs
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        if self.lights.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = self.lights.iter().map(|light| light.pdf_value(ray)).sum();
+        total / self.lights.len() as f64
+    }
+
+    /// Samples a point on a randomly chosen light
+    fn random(&self, origin: Point3, time: f64) -> Vec3 {
+        if self.lights.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = ((random() * self.lights.len() as f64) as usize).min(self.lights.len() - 1);
+        self.lights[index].random(origin, time)
     }
 }